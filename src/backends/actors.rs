@@ -0,0 +1,207 @@
+//! Backend con actores. Cada actor es un hilo con un buzón propio y procesa sus mensajes de a
+//! uno, sin compartir estado con los demás:
+//!
+//! * Lectores: reciben archivos del coordinador, los leen en lotes de líneas y reparten los
+//!   lotes entre los parsers.
+//! * Parsers: parsean cada lote y envían el resultado parcial al agregador del sitio.
+//! * Agregadores: uno por sitio, dueños de los contadores de los archivos de ese sitio.
+//! * Coordinador: reparte los archivos entre los lectores y junta los resultados de los
+//!   agregadores. Los totals se calculan sobre el `ResultData` combinado.
+
+use super::{
+    aggregate, file_order, parse_batch, read_batches, read_message, site_ids, Batch, Message,
+    PartialFiles,
+};
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::intern::Id;
+use crate::processors::{LineOptions, TimedResult};
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+use std::thread;
+use std::time::Instant;
+
+/// Cantidad de actores lectores.
+const READERS: usize = 2;
+
+/// Mensajes que puede haber en el buzón de cada parser y de cada agregador.
+const MAILBOX_CAPACITY: usize = 4;
+
+/// Direcciones de los agregadores y el agregador que corresponde a cada archivo.
+#[derive(Clone)]
+struct Aggregators {
+    mailboxes: Vec<SyncSender<Message>>,
+    /// `slots[index]` es la posición en `mailboxes` del agregador del archivo `index` y la
+    /// posición del archivo entre los de ese agregador.
+    slots: Vec<(usize, usize)>,
+}
+
+impl Aggregators {
+    /// Envía un mensaje al agregador del sitio de un archivo.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `message` - Mensaje a enviar.
+    ///
+    /// # Returns
+    ///
+    /// `false` si el agregador ya terminó.
+    fn send(&self, index: usize, message: Message) -> bool {
+        let (slot, position) = self.slots[index];
+        self.mailboxes[slot]
+            .send(message.with_index(position))
+            .is_ok()
+    }
+}
+
+/// Actor lector: lee cada archivo que recibe y reparte sus lotes entre los parsers.
+///
+/// # Arguments
+///
+/// * `mailbox` - Buzón con las posiciones de los archivos a leer.
+/// * `parsers` - Buzones de los parsers.
+/// * `aggregators` - Agregadores de los sitios.
+/// * `paths` - Archivos de entrada.
+/// * `first_parser` - Parser al que se envía el primer lote, para repartir entre lectores.
+fn reader(
+    mailbox: Receiver<usize>,
+    parsers: Vec<SyncSender<Batch>>,
+    aggregators: Aggregators,
+    paths: &[PathBuf],
+    first_parser: usize,
+) {
+    let mut next_parser = first_parser;
+    for index in mailbox {
+        let read = read_batches(&paths[index], |lines| {
+            let parser = &parsers[next_parser % parsers.len()];
+            next_parser += 1;
+            parser.send(Batch { index, lines }).is_ok()
+        });
+        let message = read_message(index, read);
+        if !aggregators.send(index, message) {
+            break;
+        }
+    }
+}
+
+/// Actor parser: parsea cada lote que recibe y envía el resultado al agregador del sitio.
+///
+/// # Arguments
+///
+/// * `mailbox` - Buzón con los lotes a parsear.
+/// * `aggregators` - Agregadores de los sitios.
+/// * `sites` - Identificador del sitio de cada archivo.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+fn parser(
+    mailbox: Receiver<Batch>,
+    aggregators: Aggregators,
+    sites: &[Id],
+    config: &Config,
+    start: Instant,
+) {
+    for Batch { index, lines } in mailbox {
+        let message = parse_batch(
+            index,
+            &lines,
+            sites[index],
+            LineOptions::from(config),
+            start,
+        );
+        if !aggregators.send(index, message) {
+            break;
+        }
+    }
+}
+
+/// Procesa los archivos con actores lectores, parsers (uno por worker) y agregadores (uno por
+/// sitio), coordinados desde el hilo que llama.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+///
+/// # Returns
+///
+/// El resultado de cada archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir alguno de los archivos.
+pub(crate) fn process(
+    paths: &[PathBuf],
+    config: &Config,
+    start: Instant,
+) -> io::Result<Vec<TimedResult>> {
+    let sites = site_ids(paths);
+    let mut site_slots: HashMap<Id, usize> = HashMap::new();
+    // `site_files[slot]` son las posiciones de los archivos del sitio del agregador `slot`
+    let mut site_files: Vec<Vec<usize>> = Vec::new();
+    let slots: Vec<(usize, usize)> = sites
+        .iter()
+        .enumerate()
+        .map(|(index, site)| {
+            let slot = *site_slots.entry(*site).or_insert_with(|| {
+                site_files.push(Vec::new());
+                site_files.len() - 1
+            });
+            site_files[slot].push(index);
+            (slot, site_files[slot].len() - 1)
+        })
+        .collect();
+
+    thread::scope(|scope| {
+        let (mailboxes, aggregator_handles): (Vec<_>, Vec<_>) = site_files
+            .iter()
+            .map(|files| {
+                let (sender, receiver) = sync_channel(MAILBOX_CAPACITY);
+                let handle = scope.spawn(move || aggregate(receiver, files.len()));
+                (sender, handle)
+            })
+            .unzip();
+        let aggregators = Aggregators { mailboxes, slots };
+
+        let parsers: Vec<SyncSender<Batch>> = (0..rayon::current_num_threads())
+            .map(|_| {
+                let (sender, receiver) = sync_channel(MAILBOX_CAPACITY);
+                let (aggregators, sites) = (aggregators.clone(), &sites);
+                scope.spawn(move || parser(receiver, aggregators, sites, config, start));
+                sender
+            })
+            .collect();
+
+        let readers: Vec<_> = (0..READERS)
+            .map(|first_parser| {
+                let (sender, receiver) = channel();
+                let (parsers, aggregators) = (parsers.clone(), aggregators.clone());
+                scope.spawn(move || reader(receiver, parsers, aggregators, paths, first_parser));
+                sender
+            })
+            .collect();
+        drop((parsers, aggregators));
+
+        for (position, index) in file_order(paths, config.schedule).into_iter().enumerate() {
+            if is_cancelled() || readers[position % READERS].send(index).is_err() {
+                break;
+            }
+        }
+        drop(readers);
+
+        let mut partial_files = PartialFiles::new(paths.len());
+        for (handle, files) in aggregator_handles.into_iter().zip(&site_files) {
+            match handle.join() {
+                Ok(aggregated) => partial_files.merge(aggregated, files),
+                Err(_) => {
+                    eprintln!("Error en un agregador");
+                    std::process::exit(1);
+                }
+            }
+        }
+        partial_files.into_results(paths)
+    })
+}
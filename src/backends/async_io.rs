@@ -0,0 +1,299 @@
+//! Backend con lectura asincrónica. Un runtime de tokio lee varios archivos a la vez con unas
+//! pocas tareas de I/O y entrega lotes de líneas completas al pool de parseo de rayon, así la
+//! latencia del almacenamiento no bloquea a los workers que parsean.
+//!
+//! Cada lote reserva un permiso antes de leerse y lo libera al terminar de parsearse, por lo
+//! que la lectura puede adelantarse al parseo en a lo sumo dos lotes por worker.
+
+use super::{aggregate, file_order, parse_batch, read_message, site_ids, Message, BATCH_BYTES};
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::processors::{is_gzip, LineOptions, TimedResult};
+use flate2::write::GzDecoder;
+use rayon::ThreadPool;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+use tokio::runtime::Builder;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinSet;
+
+/// Cantidad de tareas de I/O por defecto, si no se indica `--io-workers`.
+const IO_TASKS: usize = 4;
+
+/// Lotes en vuelo por cada worker de parseo.
+const BATCHES_PER_WORKER: usize = 2;
+
+/// Tamaño de los bloques que se leen de un archivo comprimido. Es menor que el de los lotes
+/// porque cada bloque puede ocupar varias veces su tamaño al descomprimirse.
+const GZIP_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Prefetched: Lote de líneas completas leído por una tarea de I/O, con su permiso.
+struct Prefetched {
+    index: usize,
+    lines: Vec<u8>,
+    /// Se libera al terminar de parsear el lote.
+    permit: OwnedSemaphorePermit,
+}
+
+/// IoTask: Estado compartido por las tareas de I/O.
+#[derive(Clone)]
+struct IoTask {
+    paths: Arc<Vec<PathBuf>>,
+    /// Posiciones de los archivos pendientes de leer, en el orden configurado.
+    pending: Arc<Mutex<VecDeque<usize>>>,
+    permits: Arc<Semaphore>,
+    batches: Sender<Prefetched>,
+    messages: Sender<Message>,
+}
+
+/// Decoder: Descomprime, si corresponde, los bytes leídos de un archivo.
+enum Decoder {
+    /// Archivo sin comprimir.
+    Plain,
+    /// Archivo gzip, que se descomprime a medida que se leen sus bloques.
+    Gzip(Box<GzDecoder<Vec<u8>>>),
+}
+
+impl Decoder {
+    /// Agrega a `lines` el contenido de un bloque leído.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - Bytes leídos del archivo.
+    /// * `lines` - Contenido pendiente de enviar.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve un error si el contenido comprimido es inválido.
+    fn push(&mut self, chunk: &[u8], lines: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Decoder::Plain => lines.extend_from_slice(chunk),
+            Decoder::Gzip(decoder) => {
+                decoder.write_all(chunk)?;
+                lines.append(decoder.get_mut());
+            }
+        }
+        Ok(())
+    }
+
+    /// Agrega a `lines` lo que quedó en el decoder al terminar el archivo.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - Contenido pendiente de enviar.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve un error si el contenido comprimido está incompleto.
+    fn finish(&mut self, lines: &mut Vec<u8>) -> io::Result<()> {
+        if let Decoder::Gzip(decoder) = self {
+            decoder.try_finish()?;
+            lines.append(decoder.get_mut());
+        }
+        Ok(())
+    }
+}
+
+/// Separa del contenido pendiente un lote de líneas completas de a lo sumo `max` bytes. Si la
+/// primera línea es más larga, el lote tiene solo esa línea.
+///
+/// # Arguments
+///
+/// * `pending` - Contenido leído y no enviado. Se queda con lo que no entró en el lote.
+/// * `max` - Tamaño máximo del lote.
+///
+/// # Returns
+///
+/// Las líneas del lote, o `None` si no hay ninguna completa.
+pub(crate) fn take_lines(pending: &mut Vec<u8>, max: usize) -> Option<Vec<u8>> {
+    let limit = max.min(pending.len());
+    let end = match pending[..limit].iter().rposition(|byte| *byte == b'\n') {
+        Some(end) => end,
+        None => limit + pending[limit..].iter().position(|byte| *byte == b'\n')?,
+    } + 1;
+    Some(pending.drain(..end).collect())
+}
+
+impl IoTask {
+    /// Lee archivos de la cola hasta que se vacía.
+    async fn run(self) {
+        loop {
+            let next = self
+                .pending
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .pop_front();
+            let Some(index) = next else {
+                break;
+            };
+            let message = read_message(index, self.read_file(index).await);
+            if self.messages.send(message).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Lee un archivo en bloques y envía sus líneas en lotes al pool de parseo.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    ///
+    /// # Returns
+    ///
+    /// La cantidad de lotes enviados y si se dejó de leer por una cancelación.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve un error si no puede abrir el archivo.
+    async fn read_file(&self, index: usize) -> io::Result<(usize, bool)> {
+        let path = &self.paths[index];
+        let mut file = File::open(path).await?;
+        file.set_max_buf_size(BATCH_BYTES);
+        let (mut decoder, chunk_bytes) = if is_gzip(path) {
+            let decoder = Decoder::Gzip(Box::new(GzDecoder::new(Vec::new())));
+            (decoder, GZIP_CHUNK_BYTES)
+        } else {
+            (Decoder::Plain, BATCH_BYTES)
+        };
+
+        let mut parts = 0;
+        let mut pending = Vec::with_capacity(BATCH_BYTES);
+        let mut chunk = vec![0; chunk_bytes];
+        let mut eof = false;
+        loop {
+            if is_cancelled() {
+                return Ok((parts, true));
+            }
+            let Ok(permit) = Arc::clone(&self.permits).acquire_owned().await else {
+                break;
+            };
+            let lines = loop {
+                // se lee solo si lo pendiente no alcanza para un lote, así un bloque que se
+                // descomprime en mucho más que un lote se envía en varios
+                if pending.len() >= BATCH_BYTES {
+                    if let Some(lines) = take_lines(&mut pending, BATCH_BYTES) {
+                        break lines;
+                    }
+                }
+                if eof {
+                    break std::mem::take(&mut pending);
+                }
+                let read = match file.read(&mut chunk).await {
+                    Ok(0) => decoder.finish(&mut pending).map(|_| 0),
+                    Ok(read) => decoder.push(&chunk[..read], &mut pending).map(|_| read),
+                    Err(e) => Err(e),
+                };
+                match read {
+                    Ok(0) => eof = true,
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("Error al leer línea del archivo: {}", e);
+                        eof = true;
+                    }
+                }
+            };
+            if lines.is_empty() {
+                break;
+            }
+            let batch = Prefetched {
+                index,
+                lines,
+                permit,
+            };
+            if self.batches.send(batch).is_err() {
+                break;
+            }
+            parts += 1;
+        }
+        Ok((parts, false))
+    }
+}
+
+/// Procesa los archivos leyéndolos con tareas asincrónicas y parseando los lotes en un pool de
+/// rayon.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pool` - Pool donde se parsean los lotes.
+/// * `start` - Inicio del procesamiento.
+///
+/// # Returns
+///
+/// El resultado de cada archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir alguno de los archivos.
+pub(crate) fn process(
+    paths: &[PathBuf],
+    config: &Config,
+    pool: &ThreadPool,
+    start: Instant,
+) -> io::Result<Vec<TimedResult>> {
+    let io_tasks = config.io_workers.unwrap_or(IO_TASKS);
+    let runtime = Builder::new_current_thread()
+        .max_blocking_threads(io_tasks)
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("Error al crear el runtime de I/O: {}", e);
+            std::process::exit(1);
+        });
+    let sites = site_ids(paths);
+    let (batch_sender, batches) = channel();
+    let (message_sender, messages) = channel();
+    let task = IoTask {
+        paths: Arc::new(paths.to_vec()),
+        pending: Arc::new(Mutex::new(file_order(paths, config.schedule).into())),
+        permits: Arc::new(Semaphore::new(
+            pool.current_num_threads() * BATCHES_PER_WORKER,
+        )),
+        batches: batch_sender,
+        messages: message_sender.clone(),
+    };
+
+    let partial_files = thread::scope(|scope| {
+        let aggregator = scope.spawn(move || aggregate(messages, paths.len()));
+        scope.spawn(move || {
+            runtime.block_on(async {
+                let mut tasks = JoinSet::new();
+                for _ in 0..io_tasks {
+                    tasks.spawn(task.clone().run());
+                }
+                drop(task);
+                while tasks.join_next().await.is_some() {}
+            })
+        });
+        pool.in_place_scope(|parsers| {
+            for Prefetched {
+                index,
+                lines,
+                permit,
+            } in batches
+            {
+                let (messages, site) = (message_sender.clone(), sites[index]);
+                parsers.spawn(move |_| {
+                    let message =
+                        parse_batch(index, &lines, site, LineOptions::from(config), start);
+                    drop((lines, permit));
+                    let _ = messages.send(message);
+                });
+            }
+        });
+        drop(message_sender);
+        aggregator.join().unwrap_or_else(|_| {
+            eprintln!("Error en el agregador");
+            std::process::exit(1);
+        })
+    });
+    partial_files.into_results(paths)
+}
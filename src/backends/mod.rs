@@ -0,0 +1,470 @@
+//! Este módulo contiene backends de procesamiento alternativos a rayon. Se eligen con
+//! `--backend` y generan los mismos resultados por archivo que el backend por defecto.
+
+pub(crate) mod actors;
+pub(crate) mod async_io;
+pub(crate) mod pipeline;
+pub(crate) mod sharded;
+pub(crate) mod threads;
+
+use crate::cancel::is_cancelled;
+use crate::config::{Config, Schedule};
+use crate::intern::{intern, Id};
+use crate::processors::{
+    combine_partials, get_site_name, is_gzip, not_processed, open_lines, process_block,
+    processed_result, schedule_largest_first, split_ranges, LineOptions, TimedResult,
+};
+use crate::structs::{Accumulator, ByteRange, FileStats, FileStatus};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::time::{Duration, Instant};
+
+/// Tamaño aproximado de cada lote de líneas que lee un lector.
+const BATCH_BYTES: usize = 1024 * 1024;
+
+/// Devuelve las posiciones de los archivos en el orden en que se deben procesar.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos de entrada.
+/// * `schedule` - Orden configurado.
+fn file_order(paths: &[PathBuf], schedule: Schedule) -> Vec<usize> {
+    match schedule {
+        Schedule::Largest => schedule_largest_first(paths)
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect(),
+        Schedule::Input => (0..paths.len()).collect(),
+    }
+}
+
+/// Devuelve el identificador del sitio de cada archivo.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos de entrada.
+fn site_ids(paths: &[PathBuf]) -> Vec<Id> {
+    paths
+        .iter()
+        .map(|path| intern(&get_site_name(path)))
+        .collect()
+}
+
+/// Batch: Lote de líneas completas de un archivo.
+struct Batch {
+    index: usize,
+    lines: Vec<u8>,
+}
+
+/// Message: Mensaje que recibe un agregador.
+enum Message {
+    /// El lector terminó un archivo, enviando `parts` lotes.
+    Read {
+        index: usize,
+        parts: usize,
+        interrupted: bool,
+    },
+    /// El lector no pudo abrir un archivo.
+    Failed { index: usize, error: io::Error },
+    /// Un parser procesó un lote.
+    Parsed {
+        index: usize,
+        partial: (Accumulator, FileStats),
+        interrupted: bool,
+        start: Duration,
+        finish: Duration,
+    },
+}
+
+impl Message {
+    /// Devuelve el mismo mensaje con otra posición de archivo, para los agregadores que
+    /// numeran solo sus propios archivos.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Nueva posición del archivo.
+    fn with_index(mut self, position: usize) -> Self {
+        match &mut self {
+            Message::Read { index, .. }
+            | Message::Failed { index, .. }
+            | Message::Parsed { index, .. } => *index = position,
+        }
+        self
+    }
+}
+
+/// Genera el mensaje para el agregador cuando el lector termina un archivo.
+///
+/// # Arguments
+///
+/// * `index` - Posición del archivo.
+/// * `read` - Cantidad de lotes enviados y si se dejó de leer por una cancelación, o el error
+///   al abrir el archivo.
+fn read_message(index: usize, read: io::Result<(usize, bool)>) -> Message {
+    match read {
+        Ok((parts, interrupted)) => Message::Read {
+            index,
+            parts,
+            interrupted,
+        },
+        Err(error) => Message::Failed { index, error },
+    }
+}
+
+/// Parsea un lote de líneas y genera el mensaje para el agregador.
+///
+/// # Arguments
+///
+/// * `index` - Posición del archivo del lote.
+/// * `lines` - Líneas del lote.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `start` - Inicio del procesamiento.
+fn parse_batch(
+    index: usize,
+    lines: &[u8],
+    site: Id,
+    options: LineOptions,
+    start: Instant,
+) -> Message {
+    let batch_start = start.elapsed();
+    let (accumulator, stats, interrupted) = process_block(lines, site, options, None);
+    Message::Parsed {
+        index,
+        partial: (accumulator, stats),
+        interrupted,
+        start: batch_start,
+        finish: start.elapsed(),
+    }
+}
+
+/// Combina los resultados de cada archivo hasta que se cierra el canal.
+///
+/// # Arguments
+///
+/// * `messages` - Canal desde los lectores y los parsers.
+/// * `count` - Cantidad de archivos.
+fn aggregate(messages: Receiver<Message>, count: usize) -> PartialFiles {
+    let mut partial_files = PartialFiles::new(count);
+    for message in messages {
+        match message {
+            Message::Read {
+                index,
+                parts,
+                interrupted,
+            } => {
+                partial_files.set_parts(index, parts);
+                if interrupted {
+                    partial_files.interrupt(index);
+                }
+            }
+            Message::Failed { index, error } => partial_files.fail(index, error),
+            Message::Parsed {
+                index,
+                partial,
+                interrupted,
+                start,
+                finish,
+            } => partial_files.add(index, partial, interrupted, start, finish),
+        }
+    }
+    partial_files
+}
+
+/// Lee un archivo en lotes de líneas completas.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `send` - Recibe cada lote. Devuelve `false` si no se pueden enviar más lotes.
+///
+/// # Returns
+///
+/// La cantidad de lotes enviados y si se dejó de leer por una cancelación.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir el archivo.
+fn read_batches(path: &Path, mut send: impl FnMut(Vec<u8>) -> bool) -> io::Result<(usize, bool)> {
+    let mut reader = open_lines(path)?;
+    let mut parts = 0;
+    let mut eof = false;
+    while !eof {
+        if is_cancelled() {
+            return Ok((parts, true));
+        }
+        let mut lines = Vec::with_capacity(BATCH_BYTES);
+        while !eof && lines.len() < BATCH_BYTES {
+            match reader.read_until(b'\n', &mut lines) {
+                Ok(0) => eof = true,
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Error al leer línea del archivo: {}", e);
+                    eof = true;
+                }
+            }
+        }
+        if lines.is_empty() || !send(lines) {
+            break;
+        }
+        parts += 1;
+    }
+    Ok((parts, false))
+}
+
+/// Task: Parte de un archivo que se procesa de forma independiente.
+enum Task {
+    /// Rango de bytes de un archivo regular.
+    Range(usize, ByteRange),
+    /// Archivo completo, para los comprimidos y los que no son archivos regulares.
+    Whole(usize),
+}
+
+impl Task {
+    /// Devuelve la posición del archivo de la tarea.
+    fn index(&self) -> usize {
+        match self {
+            Task::Range(index, _) | Task::Whole(index) => *index,
+        }
+    }
+}
+
+/// Divide los archivos en tareas y registra cuántas partes tiene cada uno. Un archivo que no se
+/// puede abrir se registra como fallido y no genera tareas.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos de entrada.
+/// * `config` - Configuración del procesamiento.
+/// * `partial_files` - Resultados parciales donde se registra la cantidad de partes.
+fn split_tasks(
+    paths: &[PathBuf],
+    config: &Config,
+    partial_files: &mut PartialFiles,
+) -> VecDeque<Task> {
+    let mut tasks = VecDeque::new();
+    for index in file_order(paths, config.schedule) {
+        let path = &paths[index];
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                partial_files.fail(index, e);
+                continue;
+            }
+        };
+        if is_gzip(path) || !metadata.is_file() {
+            partial_files.set_parts(index, 1);
+            tasks.push_back(Task::Whole(index));
+            continue;
+        }
+        let ranges = match split_ranges(path, config.chunk_size()) {
+            Ok(ranges) => ranges,
+            Err(e) => {
+                partial_files.fail(index, e);
+                continue;
+            }
+        };
+        partial_files.set_parts(index, ranges.len());
+        tasks.extend(ranges.into_iter().map(|range| Task::Range(index, range)));
+    }
+    tasks
+}
+
+/// Abre el lector de las líneas de una tarea.
+///
+/// # Arguments
+///
+/// * `task` - Tarea a leer.
+/// * `paths` - Archivos de entrada.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir el archivo.
+fn task_reader(task: &Task, paths: &[PathBuf]) -> io::Result<Box<dyn BufRead + Send>> {
+    match *task {
+        Task::Range(index, range) => {
+            let mut file = File::open(&paths[index])?;
+            file.seek(SeekFrom::Start(range.start))?;
+            Ok(Box::new(BufReader::new(file.take(range.end - range.start))))
+        }
+        Task::Whole(index) => open_lines(&paths[index]),
+    }
+}
+
+/// PartialFile: Resultado de un archivo mientras se procesan sus partes.
+#[derive(Default)]
+struct PartialFile {
+    accumulator: Accumulator,
+    stats: FileStats,
+    /// Cantidad de partes en las que se dividió el archivo, una vez que se conoce.
+    parts: Option<usize>,
+    done: usize,
+    interrupted: bool,
+    /// Error al abrir el archivo, si no se pudo procesar.
+    error: Option<io::Error>,
+    start: Option<Duration>,
+    finish: Duration,
+}
+
+/// PartialFiles: Resultados parciales de todos los archivos, indexados por su posición en la
+/// lista de entrada.
+pub(crate) struct PartialFiles {
+    files: Vec<PartialFile>,
+}
+
+impl PartialFiles {
+    /// Crea los resultados parciales vacíos.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Cantidad de archivos.
+    pub(crate) fn new(count: usize) -> Self {
+        PartialFiles {
+            files: (0..count).map(|_| PartialFile::default()).collect(),
+        }
+    }
+
+    /// Registra en cuántas partes se dividió un archivo.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `parts` - Cantidad de partes.
+    pub(crate) fn set_parts(&mut self, index: usize, parts: usize) {
+        self.files[index].parts = Some(parts);
+    }
+
+    /// Registra que un archivo se dejó de leer por una cancelación.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    pub(crate) fn interrupt(&mut self, index: usize) {
+        self.files[index].interrupted = true;
+    }
+
+    /// Registra que no se pudo abrir un archivo. Se conserva el primer error.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `error` - Error al abrirlo.
+    pub(crate) fn fail(&mut self, index: usize, error: io::Error) {
+        self.files[index].error.get_or_insert(error);
+    }
+
+    /// Suma el resultado de una parte de un archivo.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `partial` - `Accumulator` y métricas de la parte.
+    /// * `interrupted` - Si la parte se dejó de procesar por una cancelación.
+    /// * `start` - Momento en que se empezó la parte, desde el inicio del procesamiento.
+    /// * `finish` - Momento en que se terminó la parte, desde el inicio del procesamiento.
+    pub(crate) fn add(
+        &mut self,
+        index: usize,
+        partial: (Accumulator, FileStats),
+        interrupted: bool,
+        start: Duration,
+        finish: Duration,
+    ) {
+        let file = &mut self.files[index];
+        let current = (std::mem::take(&mut file.accumulator), file.stats);
+        (file.accumulator, file.stats) = combine_partials(current, partial);
+        file.done += 1;
+        file.interrupted |= interrupted;
+        file.start = Some(file.start.map_or(start, |first| first.min(start)));
+        file.finish = file.finish.max(finish);
+    }
+
+    /// Reemplaza el `Accumulator` de un archivo, para los backends que agregan fuera de las
+    /// partes.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `accumulator` - Contadores del archivo.
+    pub(crate) fn set_accumulator(&mut self, index: usize, accumulator: Accumulator) {
+        self.files[index].accumulator = accumulator;
+    }
+
+    /// Combina los resultados parciales de otro agregador, que solo tiene algunos de los
+    /// archivos.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Resultados parciales a combinar.
+    /// * `indices` - `indices[i]` es la posición en la lista completa del archivo `i` de `other`.
+    pub(crate) fn merge(&mut self, other: PartialFiles, indices: &[usize]) {
+        for (&index, other) in indices.iter().zip(other.files) {
+            let file = &mut self.files[index];
+            file.parts = file.parts.or(other.parts);
+            file.interrupted |= other.interrupted;
+            if let Some(error) = other.error {
+                file.error.get_or_insert(error);
+            }
+            let Some(start) = other.start else {
+                continue;
+            };
+            let current = (std::mem::take(&mut file.accumulator), file.stats);
+            (file.accumulator, file.stats) =
+                combine_partials(current, (other.accumulator, other.stats));
+            file.done += other.done;
+            file.start = Some(file.start.map_or(start, |first| first.min(start)));
+            file.finish = file.finish.max(other.finish);
+        }
+    }
+
+    /// Genera el resultado de cada archivo.
+    ///
+    /// Un archivo del que no se procesó ninguna parte queda como `NotProcessed`, y uno al que
+    /// le faltan partes o tiene alguna interrumpida queda como `Partial`.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Archivos de entrada, en el mismo orden que los índices.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve el error del primer archivo que no se pudo abrir, con su ruta.
+    pub(crate) fn into_results(self, paths: &[PathBuf]) -> io::Result<Vec<TimedResult>> {
+        for (file, path) in self.files.iter().zip(paths) {
+            if let Some(error) = &file.error {
+                return Err(io::Error::new(
+                    error.kind(),
+                    format!("{}: {}", path.display(), error),
+                ));
+            }
+        }
+        Ok(self
+            .files
+            .into_iter()
+            .zip(paths)
+            .enumerate()
+            .map(|(index, (file, path))| {
+                let Some(start) = file.start else {
+                    // Un archivo vacío no tiene partes pero está completo.
+                    let result = if file.parts == Some(0) {
+                        processed_result(path, file.accumulator, file.stats, FileStatus::Complete)
+                    } else {
+                        not_processed(path)
+                    };
+                    return (index, Duration::ZERO, Duration::ZERO, result);
+                };
+                let status = if file.interrupted || file.parts != Some(file.done) {
+                    FileStatus::Partial
+                } else {
+                    FileStatus::Complete
+                };
+                let mut result = processed_result(path, file.accumulator, file.stats, status);
+                result.stats.elapsed_ns = file.finish.saturating_sub(start).as_nanos() as u64;
+                (index, start, file.finish, result)
+            })
+            .collect())
+    }
+}
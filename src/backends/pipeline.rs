@@ -0,0 +1,110 @@
+//! Backend en pipeline. Un hilo lector envía lotes de líneas por un canal acotado a un pool de
+//! parsers, que envían sus resultados parciales por otro canal acotado a un hilo agregador.
+//! Los canales acotados frenan al lector si los parsers no dan abasto.
+
+use super::{
+    aggregate, file_order, parse_batch, read_batches, read_message, site_ids, Batch, Message,
+};
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::intern::Id;
+use crate::processors::{LineOptions, TimedResult};
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Lotes que puede haber en cada canal por cada parser.
+const BATCHES_PER_WORKER: usize = 2;
+
+/// Etapa de parseo: toma lotes hasta que el lector cierra el canal.
+///
+/// # Arguments
+///
+/// * `batches` - Canal desde el lector, compartido por los parsers.
+/// * `messages` - Canal hacia el agregador.
+/// * `sites` - Identificador del sitio de cada archivo.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+fn parse_batches(
+    batches: &Mutex<Receiver<Batch>>,
+    messages: SyncSender<Message>,
+    sites: &[Id],
+    config: &Config,
+    start: Instant,
+) {
+    loop {
+        let batch = batches.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        let Ok(Batch { index, lines }) = batch else {
+            break;
+        };
+        let message = parse_batch(
+            index,
+            &lines,
+            sites[index],
+            LineOptions::from(config),
+            start,
+        );
+        if messages.send(message).is_err() {
+            break;
+        }
+    }
+}
+
+/// Procesa los archivos con un lector, un parser por worker y un agregador.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+///
+/// # Returns
+///
+/// El resultado de cada archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir alguno de los archivos.
+pub(crate) fn process(
+    paths: &[PathBuf],
+    config: &Config,
+    start: Instant,
+) -> io::Result<Vec<TimedResult>> {
+    let workers = rayon::current_num_threads();
+    let sites = site_ids(paths);
+    let (batch_sender, batch_receiver) = sync_channel(workers * BATCHES_PER_WORKER);
+    let (message_sender, message_receiver) = sync_channel(workers * BATCHES_PER_WORKER);
+    let batch_receiver = Mutex::new(batch_receiver);
+
+    let partial_files = thread::scope(|scope| {
+        let aggregator = scope.spawn(move || aggregate(message_receiver, paths.len()));
+        for _ in 0..workers {
+            let messages = message_sender.clone();
+            let (batches, sites) = (&batch_receiver, &sites);
+            scope.spawn(move || parse_batches(batches, messages, sites, config, start));
+        }
+        let reader_messages = message_sender;
+        scope.spawn(move || {
+            for index in file_order(paths, config.schedule) {
+                if is_cancelled() {
+                    break;
+                }
+                let read = read_batches(&paths[index], |lines| {
+                    batch_sender.send(Batch { index, lines }).is_ok()
+                });
+                let message = read_message(index, read);
+                if reader_messages.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+        aggregator.join().unwrap_or_else(|_| {
+            eprintln!("Error en el agregador del pipeline");
+            std::process::exit(1);
+        })
+    });
+    partial_files.into_results(paths)
+}
@@ -0,0 +1,249 @@
+//! Backend con un mapa compartido dividido en shards. Los workers de rayon procesan las
+//! tareas y suman cada línea directamente en el mapa compartido, en lugar de acumular en un
+//! `Accumulator` propio. Cada shard tiene su propio `Mutex` y registra cuánto se esperó por él.
+//! Los contadores de etiquetas se reparten por archivo y etiqueta, y los de sitio por archivo
+//! y worker.
+
+use super::{site_ids, split_tasks, task_reader, PartialFiles, Task};
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::intern::Id;
+use crate::processors::{elapsed_ns, parse_counts, LineOptions, TimedResult, QUESTIONS_PER_LINE};
+use crate::report::ShardReport;
+use crate::structs::{Accumulator, FileStats, TagData};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+
+/// Cantidad de shards por defecto.
+pub(crate) const DEFAULT_SHARDS: usize = 16;
+
+/// Clave de un contador: posición del archivo y etiqueta, o `None` para el sitio del archivo.
+/// Un mismo contador de sitio puede estar en varios shards, uno por worker.
+type Key = (usize, Option<Id>);
+
+/// Shard: Parte del mapa compartido, con sus métricas de contención.
+#[derive(Default)]
+struct Shard {
+    counters: Mutex<HashMap<Key, TagData>>,
+    locks: AtomicU64,
+    contended: AtomicU64,
+    wait_ns: AtomicU64,
+}
+
+impl Shard {
+    /// Toma el lock del shard. Si está tomado por otro worker, mide cuánto se espera.
+    fn lock(&self) -> MutexGuard<'_, HashMap<Key, TagData>> {
+        self.locks.fetch_add(1, Ordering::Relaxed);
+        if let Ok(guard) = self.counters.try_lock() {
+            return guard;
+        }
+        self.contended.fetch_add(1, Ordering::Relaxed);
+        let wait_start = Instant::now();
+        let guard = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        self.wait_ns
+            .fetch_add(elapsed_ns(wait_start), Ordering::Relaxed);
+        guard
+    }
+}
+
+/// ShardedMap: Mapa de contadores compartido por todos los workers.
+struct ShardedMap {
+    shards: Vec<Shard>,
+}
+
+impl ShardedMap {
+    /// Crea un mapa vacío.
+    ///
+    /// # Arguments
+    ///
+    /// * `shards` - Cantidad de shards.
+    fn new(shards: usize) -> Self {
+        ShardedMap {
+            shards: (0..shards.max(1)).map(|_| Shard::default()).collect(),
+        }
+    }
+
+    /// Suma una pregunta con sus palabras al contador de una clave.
+    ///
+    /// Cada línea de un archivo suma en el contador de su sitio, por lo que ese contador se
+    /// reparte además por worker: cada worker suma su parte en un shard distinto y
+    /// `into_accumulators` junta las partes.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - Clave del contador.
+    /// * `words` - Cantidad de palabras de la pregunta.
+    fn add(&self, key: Key, words: u32) {
+        let (index, tag) = key;
+        let stripe = match tag {
+            Some(tag) => tag as usize + 1,
+            None => rayon::current_thread_index().unwrap_or(0),
+        };
+        let slot = index.wrapping_mul(31).wrapping_add(stripe);
+        let mut counters = self.shards[slot % self.shards.len()].lock();
+        let tag_data = counters.entry(key).or_insert_with(|| TagData::new(0, 0));
+        tag_data.questions += QUESTIONS_PER_LINE;
+        tag_data.words += words;
+    }
+
+    /// Genera las métricas de contención de cada shard.
+    fn reports(&self) -> Vec<ShardReport> {
+        self.shards
+            .iter()
+            .map(|shard| {
+                ShardReport::new(
+                    shard.locks.load(Ordering::Relaxed),
+                    shard.contended.load(Ordering::Relaxed),
+                    shard.wait_ns.load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+
+    /// Reparte los contadores en un `Accumulator` por archivo.
+    ///
+    /// # Arguments
+    ///
+    /// * `sites` - Identificador del sitio de cada archivo.
+    fn into_accumulators(self, sites: &[Id]) -> Vec<Accumulator> {
+        let mut accumulators: Vec<Accumulator> =
+            sites.iter().map(|_| Accumulator::default()).collect();
+        for shard in self.shards {
+            let counters = shard
+                .counters
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner());
+            for ((index, tag), tag_data) in counters {
+                let site = accumulators[index].sites.entry(sites[index]).or_default();
+                match tag {
+                    None => {
+                        site.questions += tag_data.questions;
+                        site.words += tag_data.words;
+                    }
+                    Some(tag) => *site.tag_mut(tag) = tag_data,
+                }
+            }
+        }
+        accumulators
+    }
+}
+
+/// Procesa las líneas de una tarea sumándolas en el mapa compartido.
+///
+/// # Arguments
+///
+/// * `task` - Tarea a procesar.
+/// * `paths` - Archivos de entrada.
+/// * `map` - Mapa compartido.
+/// * `config` - Configuración del procesamiento.
+///
+/// # Returns
+///
+/// Las métricas de la tarea y si se dejó de procesar por una cancelación.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir el archivo.
+fn process_task(
+    task: &Task,
+    paths: &[PathBuf],
+    map: &ShardedMap,
+    config: &Config,
+) -> io::Result<(FileStats, bool)> {
+    let index = task.index();
+    let reader = task_reader(task, paths)?;
+    let mut stats = FileStats::default();
+    let timed = LineOptions::from(config).timed;
+    for line in reader.lines() {
+        if is_cancelled() {
+            return Ok((stats, true));
+        }
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Error al leer línea del archivo: {}", e);
+                continue;
+            }
+        };
+        if line.is_empty() {
+            continue;
+        }
+        stats.bytes += line.len() as u64 + 1;
+        stats.lines += 1;
+
+        let parse_start = timed.then(Instant::now);
+        let counts = parse_counts(line.as_bytes(), config.parser);
+        let aggregation_start = parse_start.map(|start| {
+            stats.parse_ns += elapsed_ns(start);
+            Instant::now()
+        });
+        match counts {
+            Ok((words, tags)) => {
+                map.add((index, None), words);
+                for tag in tags {
+                    map.add((index, Some(tag)), words);
+                }
+            }
+            Err(e) => eprintln!("Error al analizar JSON en línea del archivo: {}", e),
+        }
+        if let Some(start) = aggregation_start {
+            stats.aggregation_ns += elapsed_ns(start);
+        }
+    }
+    Ok((stats, false))
+}
+
+/// Procesa los archivos con los workers de rayon sumando en un mapa compartido.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+///
+/// # Returns
+///
+/// El resultado de cada archivo y las métricas de contención de cada shard.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir alguno de los archivos.
+pub(crate) fn process(
+    paths: &[PathBuf],
+    config: &Config,
+    start: Instant,
+) -> io::Result<(Vec<TimedResult>, Vec<ShardReport>)> {
+    let sites = site_ids(paths);
+    let mut partial_files = PartialFiles::new(paths.len());
+    let tasks: Vec<Task> = split_tasks(paths, config, &mut partial_files).into();
+    let map = ShardedMap::new(config.shards.unwrap_or(DEFAULT_SHARDS));
+
+    let done: Vec<_> = tasks
+        .par_iter()
+        .map(|task| {
+            let task_start = start.elapsed();
+            let processed = process_task(task, paths, &map, config);
+            (task.index(), processed, task_start, start.elapsed())
+        })
+        .collect();
+    for (index, processed, task_start, task_finish) in done {
+        match processed {
+            Ok((stats, interrupted)) => {
+                let partial = (Accumulator::default(), stats);
+                partial_files.add(index, partial, interrupted, task_start, task_finish);
+            }
+            Err(e) => partial_files.fail(index, e),
+        }
+    }
+
+    let reports = map.reports();
+    for (index, accumulator) in map.into_accumulators(&sites).into_iter().enumerate() {
+        partial_files.set_accumulator(index, accumulator);
+    }
+    Ok((partial_files.into_results(paths)?, reports))
+}
@@ -0,0 +1,161 @@
+//! Backend con hilos de la biblioteca estándar. Los archivos se dividen en tareas que los hilos
+//! toman de una cola protegida con un `Mutex`, y los resultados se envían por un canal `mpsc`.
+
+use super::{site_ids, split_tasks, task_reader, PartialFiles, Task};
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::intern::Id;
+use crate::processors::{process_block, process_reader, LineOptions, TimedResult};
+use crate::structs::{Accumulator, ByteRange, FileStats};
+use memmap2::MmapOptions;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Resultado de una tarea: posición del archivo, resultado parcial y si fue interrumpida (o el
+/// error al abrir el archivo), y los momentos en que se empezó y terminó.
+type Done = (
+    usize,
+    io::Result<(Accumulator, FileStats, bool)>,
+    Duration,
+    Duration,
+);
+
+/// Procesa un rango de un archivo mapeándolo en memoria.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `range` - Rango a procesar.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir o mapear el archivo.
+fn process_mapped_range(
+    path: &Path,
+    range: ByteRange,
+    site: Id,
+    options: LineOptions,
+) -> io::Result<(Accumulator, FileStats, bool)> {
+    let file = File::open(path)?;
+    // SAFETY: igual que en el backend de rayon, se asume que el archivo no se trunca mientras se
+    // procesa; si se trunca, acceder al mapeo produce SIGBUS.
+    let mmap = unsafe {
+        MmapOptions::new()
+            .offset(range.start)
+            .len((range.end - range.start) as usize)
+            .map(&file)?
+    };
+    Ok(process_block(&mmap, site, options, None))
+}
+
+/// Procesa una tarea. Los rangos se mapean en memoria salvo con `--no-mmap`, y los archivos
+/// completos (comprimidos o que no son archivos regulares) se leen con buffer.
+///
+/// # Arguments
+///
+/// * `task` - Tarea a procesar.
+/// * `paths` - Archivos de entrada.
+/// * `sites` - Identificador del sitio de cada archivo.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+fn run_task(task: Task, paths: &[PathBuf], sites: &[Id], config: &Config, start: Instant) -> Done {
+    let task_start = start.elapsed();
+    let index = task.index();
+    let options = LineOptions::from(config);
+    let processed = match task {
+        Task::Range(_, range) if !config.no_mmap => {
+            process_mapped_range(&paths[index], range, sites[index], options)
+        }
+        _ => task_reader(&task, paths)
+            .map(|reader| process_reader(reader, sites[index], options, None)),
+    };
+    (index, processed, task_start, start.elapsed())
+}
+
+/// Toma tareas de la cola hasta vaciarla o recibir una cancelación, y envía cada resultado.
+///
+/// # Arguments
+///
+/// * `queue` - Cola de tareas compartida.
+/// * `results` - Canal por el que se envían los resultados.
+/// * `paths` - Archivos de entrada.
+/// * `sites` - Identificador del sitio de cada archivo.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+fn worker(
+    queue: &Mutex<VecDeque<Task>>,
+    results: Sender<Done>,
+    paths: &[PathBuf],
+    sites: &[Id],
+    config: &Config,
+    start: Instant,
+) {
+    while !is_cancelled() {
+        let task = queue.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+        let Some(task) = task else {
+            break;
+        };
+        if results
+            .send(run_task(task, paths, sites, config, start))
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Procesa los archivos con un hilo por worker.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `start` - Inicio del procesamiento.
+///
+/// # Returns
+///
+/// El resultado de cada archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir alguno de los archivos.
+pub(crate) fn process(
+    paths: &[PathBuf],
+    config: &Config,
+    start: Instant,
+) -> io::Result<Vec<TimedResult>> {
+    let sites = site_ids(paths);
+    let mut partial_files = PartialFiles::new(paths.len());
+    let queue = Mutex::new(split_tasks(paths, config, &mut partial_files));
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..rayon::current_num_threads() {
+            let sender = sender.clone();
+            let (queue, sites) = (&queue, &sites);
+            scope.spawn(move || worker(queue, sender, paths, sites, config, start));
+        }
+        drop(sender);
+        for (index, processed, task_start, task_finish) in receiver {
+            match processed {
+                Ok((accumulator, stats, interrupted)) => partial_files.add(
+                    index,
+                    (accumulator, stats),
+                    interrupted,
+                    task_start,
+                    task_finish,
+                ),
+                Err(e) => partial_files.fail(index, e),
+            }
+        }
+    });
+    partial_files.into_results(paths)
+}
@@ -0,0 +1,217 @@
+//! Este módulo implementa el subcomando `bench`, que mide el procesamiento con 1, 2, 4 … N
+//! workers y recomienda una cantidad de workers.
+
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::output::write_atomic;
+use crate::pools::Pools;
+use crate::processors::{input_files, process_files, process_totals};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Tolerancia respecto del mejor tiempo para recomendar una cantidad menor de workers.
+const RECOMMENDATION_TOLERANCE: f64 = 0.05;
+
+/// Measurement: Resultado de medir el procesamiento con una cantidad de workers.
+#[derive(Debug)]
+pub(crate) struct Measurement {
+    pub(crate) workers: usize,
+    pub(crate) seconds: f64,
+    pub(crate) speedup: f64,
+    pub(crate) efficiency: f64,
+    /// Fracción serial estimada (métrica de Karp-Flatt). No está definida para 1 worker.
+    pub(crate) serial_fraction: Option<f64>,
+}
+
+impl Measurement {
+    /// Crea una medición calculando sus métricas respecto del tiempo con 1 worker.
+    ///
+    /// # Arguments
+    ///
+    /// * `workers` - Cantidad de workers medida.
+    /// * `seconds` - Tiempo medido.
+    /// * `baseline` - Tiempo de la primera medición, con 1 worker.
+    pub(crate) fn new(workers: usize, seconds: f64, baseline: f64) -> Self {
+        let speedup = baseline / seconds;
+        let p = workers as f64;
+        Measurement {
+            workers,
+            seconds,
+            speedup,
+            efficiency: speedup / p,
+            serial_fraction: (workers > 1).then(|| (1.0 / speedup - 1.0 / p) / (1.0 - 1.0 / p)),
+        }
+    }
+}
+
+/// Devuelve las cantidades de workers a medir: potencias de 2 hasta `max`, y `max`.
+///
+/// # Arguments
+///
+/// * `max` - Cantidad máxima de workers.
+pub(crate) fn worker_counts(max: usize) -> Vec<usize> {
+    let mut counts: Vec<usize> = std::iter::successors(Some(1), |n| Some(n * 2))
+        .take_while(|n| *n < max)
+        .collect();
+    counts.push(max);
+    counts
+}
+
+/// Selecciona los archivos de entrada a utilizar en la medición.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del benchmark.
+fn sample_files(config: &Config) -> Vec<PathBuf> {
+    let mut files = input_files(config);
+    files.sort();
+    if let Some(sample) = config.sample {
+        files.truncate(sample);
+    }
+    files
+}
+
+/// Mide el procesamiento completo (sin serialización) con una cantidad de workers.
+///
+/// # Arguments
+///
+/// * `files` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `workers` - Cantidad de workers del pool de parseo. El de lectura usa `--io-workers` o,
+///   si no se indica, la misma cantidad.
+/// * `repeat` - Cantidad de corridas. Se devuelve el menor tiempo.
+///
+/// # Returns
+///
+/// El menor tiempo, o `None` si se canceló durante alguna corrida, porque esa corrida
+/// procesó solo una parte de los archivos.
+fn measure(files: &[PathBuf], config: &Config, workers: usize, repeat: usize) -> Option<Duration> {
+    let pools = match Pools::new(config.io_workers.unwrap_or(workers), workers) {
+        Ok(pools) => pools,
+        Err(e) => {
+            eprintln!("Error al crear ThreadPool: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut best: Option<Duration> = None;
+    for _ in 0..repeat {
+        let start = Instant::now();
+        let mut result_data = process_files(files, config, &pools, None);
+        pools.parse().install(|| process_totals(&mut result_data));
+        let elapsed = start.elapsed();
+        if is_cancelled() {
+            return None;
+        }
+        best = Some(best.map_or(elapsed, |best| best.min(elapsed)));
+    }
+    best
+}
+
+/// Recomienda la menor cantidad de workers cuyo tiempo está dentro de la tolerancia del mejor.
+///
+/// # Arguments
+///
+/// * `measurements` - Mediciones realizadas.
+pub(crate) fn recommend(measurements: &[Measurement]) -> Option<usize> {
+    let best = measurements
+        .iter()
+        .map(|m| m.seconds)
+        .fold(f64::INFINITY, f64::min);
+    measurements
+        .iter()
+        .find(|m| m.seconds <= best * (1.0 + RECOMMENDATION_TOLERANCE))
+        .map(|m| m.workers)
+}
+
+/// Genera el CSV con las mediciones.
+///
+/// # Arguments
+///
+/// * `measurements` - Mediciones realizadas.
+pub(crate) fn to_csv(measurements: &[Measurement]) -> String {
+    let mut csv = String::from("workers,seconds,speedup,efficiency,serial_fraction\n");
+    for m in measurements {
+        let serial_fraction = m
+            .serial_fraction
+            .map(|f| format!("{:.6}", f))
+            .unwrap_or_default();
+        let _ = writeln!(
+            csv,
+            "{},{:.6},{:.6},{:.6},{}",
+            m.workers, m.seconds, m.speedup, m.efficiency, serial_fraction
+        );
+    }
+    csv
+}
+
+/// Escribe el CSV con las mediciones.
+///
+/// # Arguments
+///
+/// * `path` - Archivo CSV.
+/// * `measurements` - Mediciones realizadas.
+fn write_csv(path: &Path, measurements: &[Measurement]) {
+    match write_atomic(path, to_csv(measurements).as_bytes()) {
+        Ok(()) => eprintln!("Mediciones escritas en {}", path.display()),
+        Err(e) => eprintln!("Error al escribir {}: {}", path.display(), e),
+    }
+}
+
+/// Mide el procesamiento de una muestra de la entrada con 1, 2, 4 … N workers, imprime el
+/// speedup y la eficiencia de cada medición y recomienda una cantidad de workers.
+///
+/// Antes de medir se hace una corrida de calentamiento para que todas las mediciones
+/// encuentren los archivos en el cache del sistema operativo.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del benchmark. `workers` es la cantidad máxima a medir.
+pub fn bench(config: &Config) {
+    let config = Config {
+        state_dir: None,
+        resume: false,
+        cache_dir: None,
+        progress: false,
+        ..config.clone()
+    };
+    let files = sample_files(&config);
+    let repeat = config.repeat.unwrap_or(1);
+    eprintln!(
+        "Benchmark sobre {} archivos, {} corrida(s) por medición",
+        files.len(),
+        repeat
+    );
+
+    measure(&files, &config, config.workers, 1);
+
+    let mut measurements: Vec<Measurement> = vec![];
+    for workers in worker_counts(config.workers) {
+        // una medición cancelada no procesó todos los archivos y no se registra
+        let Some(elapsed) = measure(&files, &config, workers, repeat) else {
+            break;
+        };
+        let seconds = elapsed.as_secs_f64();
+        let baseline = measurements.first().map_or(seconds, |m| m.seconds);
+        measurements.push(Measurement::new(workers, seconds, baseline));
+    }
+
+    println!("workers\ttiempo (s)\tspeedup\teficiencia\tfracción serial");
+    for m in &measurements {
+        let serial_fraction = m
+            .serial_fraction
+            .map(|f| format!("{:.3}", f))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{}\t{:.3}\t\t{:.2}\t{:.2}\t\t{}",
+            m.workers, m.seconds, m.speedup, m.efficiency, serial_fraction
+        );
+    }
+    if let Some(workers) = recommend(&measurements) {
+        println!("Cantidad de workers recomendada: {}", workers);
+    }
+
+    if let Some(path) = &config.csv {
+        write_csv(path, &measurements);
+    }
+}
@@ -0,0 +1,163 @@
+//! Este módulo limita la memoria de las líneas leídas que todavía no se procesaron.
+//!
+//! Con `--memory-budget` los archivos se leen en lotes y cada lote reserva su tamaño en el
+//! presupuesto antes de leerse. Si no hay lugar, el lector espera a que los workers terminen
+//! lotes anteriores, por lo que la lectura no puede adelantarse al parseo.
+
+use rayon::Yield;
+use serde::Serialize;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Tamaño máximo de cada lote de líneas.
+const MAX_BATCH_BYTES: usize = 1024 * 1024;
+
+/// Tamaño mínimo de cada lote de líneas.
+const MIN_BATCH_BYTES: usize = 4 * 1024;
+
+/// Tiempo máximo de espera antes de volver a buscar trabajo pendiente en el ThreadPool.
+const WAIT: Duration = Duration::from_millis(10);
+
+/// Bytes por MB.
+const MB: f64 = 1024.0 * 1024.0;
+
+/// BudgetState: Uso actual del presupuesto.
+#[derive(Default)]
+struct BudgetState {
+    in_flight: u64,
+    peak: u64,
+    waits: u64,
+}
+
+/// MemoryBudget: Cantidad máxima de bytes de líneas leídas y no procesadas.
+pub(crate) struct MemoryBudget {
+    limit: u64,
+    state: Mutex<BudgetState>,
+    released: Condvar,
+}
+
+/// Permit: Bytes reservados en el presupuesto. Se liberan al descartarlo.
+pub(crate) struct Permit<'a> {
+    budget: &'a MemoryBudget,
+    bytes: u64,
+}
+
+/// BudgetReport: Uso del presupuesto de memoria durante una corrida.
+#[derive(Debug, Serialize)]
+pub(crate) struct BudgetReport {
+    pub(crate) limit_mb: f64,
+    /// Máximo de bytes de líneas en vuelo al mismo tiempo.
+    pub(crate) peak_in_flight_mb: f64,
+    /// Cantidad de lotes que tuvieron que esperar lugar en el presupuesto.
+    pub(crate) waits: u64,
+}
+
+impl MemoryBudget {
+    /// Crea un presupuesto vacío.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Cantidad máxima de bytes en vuelo.
+    pub(crate) fn new(limit: u64) -> Self {
+        MemoryBudget {
+            limit,
+            state: Mutex::new(BudgetState::default()),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Crea el presupuesto con el que se lee un archivo cuando no se configura
+    /// `--memory-budget`: alcanza para dos lotes del tamaño máximo por worker, así la lectura
+    /// se adelanta al parseo sin acumular el archivo entero en memoria.
+    ///
+    /// # Arguments
+    ///
+    /// * `workers` - Cantidad de workers que parsean los lotes.
+    pub(crate) fn for_workers(workers: usize) -> Self {
+        MemoryBudget::new((2 * workers.max(1) * MAX_BATCH_BYTES) as u64)
+    }
+
+    /// Devuelve el tamaño de los lotes, de forma que cada worker pueda tener dos en vuelo.
+    ///
+    /// # Arguments
+    ///
+    /// * `workers` - Cantidad de workers que parsean los lotes.
+    pub(crate) fn batch_size(&self, workers: usize) -> usize {
+        let per_batch = self.limit as usize / (2 * workers.max(1));
+        per_batch.clamp(MIN_BATCH_BYTES, MAX_BATCH_BYTES)
+    }
+
+    /// Reserva bytes en el presupuesto, esperando a que se liberen si no hay lugar.
+    ///
+    /// Mientras espera, el hilo ejecuta trabajo pendiente de su ThreadPool, así un worker que
+    /// lee no bloquea el procesamiento de los lotes que ya leyó. Si no hay nada en vuelo la
+    /// reserva se concede aunque supere el límite, para que un lote grande no espere para
+    /// siempre.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Cantidad de bytes a reservar.
+    pub(crate) fn acquire(&self, bytes: u64) -> Permit<'_> {
+        let mut waited = false;
+        loop {
+            {
+                let mut state = self.lock();
+                if state.in_flight == 0 || state.in_flight + bytes <= self.limit {
+                    state.in_flight += bytes;
+                    state.peak = state.peak.max(state.in_flight);
+                    state.waits += waited as u64;
+                    return Permit {
+                        budget: self,
+                        bytes,
+                    };
+                }
+            }
+            waited = true;
+            if rayon::yield_now() != Some(Yield::Executed) {
+                let state = self.lock();
+                if state.in_flight + bytes > self.limit {
+                    drop(self.released.wait_timeout(state, WAIT));
+                }
+            }
+        }
+    }
+
+    /// Genera el reporte del uso del presupuesto.
+    pub(crate) fn report(&self) -> BudgetReport {
+        let state = self.lock();
+        BudgetReport {
+            limit_mb: self.limit as f64 / MB,
+            peak_in_flight_mb: state.peak as f64 / MB,
+            waits: state.waits,
+        }
+    }
+
+    /// Toma el lock del estado.
+    fn lock(&self) -> MutexGuard<'_, BudgetState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Permit<'_> {
+    /// Ajusta la reserva a los bytes realmente ocupados. Un lote solo se pasa del tamaño
+    /// reservado si tiene una sola línea más larga que ese tamaño.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Cantidad de bytes ocupados.
+    pub(crate) fn resize(&mut self, bytes: u64) {
+        let mut state = self.budget.lock();
+        state.in_flight = state.in_flight - self.bytes + bytes;
+        state.peak = state.peak.max(state.in_flight);
+        self.bytes = bytes;
+        drop(state);
+        self.budget.released.notify_all();
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        self.budget.lock().in_flight -= self.bytes;
+        self.budget.released.notify_all();
+    }
+}
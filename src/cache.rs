@@ -0,0 +1,91 @@
+//! Este módulo guarda el `ResultData` parcial de cada archivo en formato binario, indexado por
+//! el hash del contenido del archivo y la versión de la herramienta.
+
+use crate::output::write_atomic;
+use crate::structs::{ResultData, SiteData, TagData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Versión del formato de las entradas del cache. Se incrementa ante cambios que alteren los
+/// resultados parciales.
+const CACHE_FORMAT_VERSION: u32 = 1;
+
+/// CacheEntry: Resultado parcial de un archivo tal como se guarda en el cache.
+#[derive(Debug, Deserialize)]
+struct CacheEntry {
+    sites: HashMap<String, SiteData>,
+    tags: HashMap<String, TagData>,
+}
+
+/// CacheEntryRef: Versión prestada de `CacheEntry` utilizada para guardarla.
+#[derive(Serialize)]
+struct CacheEntryRef<'a> {
+    sites: &'a HashMap<String, SiteData>,
+    tags: &'a HashMap<String, TagData>,
+}
+
+/// Devuelve la versión de la herramienta utilizada como parte de la clave del cache.
+fn cache_version() -> String {
+    format!("{}-{}", env!("CARGO_PKG_VERSION"), CACHE_FORMAT_VERSION)
+}
+
+/// Devuelve la ruta de la entrada del cache para un hash de contenido.
+///
+/// El nombre del sitio forma parte de la clave porque el resultado parcial depende de él.
+///
+/// # Arguments
+///
+/// * `cache_dir` - Directorio del cache.
+/// * `hash` - Hash del contenido del archivo.
+/// * `site` - Nombre del sitio del archivo.
+fn entry_path(cache_dir: &Path, hash: &str, site: &str) -> PathBuf {
+    cache_dir.join(format!("{}-{}-{}.bin", hash, site, cache_version()))
+}
+
+/// Carga del cache el resultado parcial de un archivo.
+///
+/// # Arguments
+///
+/// * `cache_dir` - Directorio del cache.
+/// * `hash` - Hash del contenido del archivo.
+/// * `site` - Nombre del sitio del archivo.
+/// * `padron` - Número de padrón del `ResultData` a generar.
+///
+/// # Returns
+///
+/// El `ResultData` guardado, o `None` si no hay una entrada válida para el archivo.
+pub(crate) fn load(cache_dir: &Path, hash: &str, site: &str, padron: u32) -> Option<ResultData> {
+    let contents = fs::read(entry_path(cache_dir, hash, site)).ok()?;
+    match bincode::deserialize::<CacheEntry>(&contents) {
+        Ok(entry) => Some(ResultData::new(padron, entry.sites, entry.tags)),
+        Err(e) => {
+            eprintln!("Entrada de cache inválida para el hash {}: {}", hash, e);
+            None
+        }
+    }
+}
+
+/// Guarda en el cache el resultado parcial de un archivo.
+///
+/// # Arguments
+///
+/// * `cache_dir` - Directorio del cache.
+/// * `hash` - Hash del contenido del archivo.
+/// * `site` - Nombre del sitio del archivo.
+/// * `data` - `ResultData` del archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede escribir la entrada.
+pub(crate) fn save(cache_dir: &Path, hash: &str, site: &str, data: &ResultData) -> io::Result<()> {
+    let entry = CacheEntryRef {
+        sites: &data.sites,
+        tags: &data.tags,
+    };
+    let contents = bincode::serialize(&entry).map_err(io::Error::other)?;
+    fs::create_dir_all(cache_dir)?;
+    write_atomic(&entry_path(cache_dir, hash, site), &contents)
+}
@@ -0,0 +1,116 @@
+//! Este módulo maneja la cancelación cooperativa del procesamiento ante SIGINT/SIGTERM.
+//!
+//! El handler de la señal solo escribe el número de señal en un pipe, que es seguro dentro de
+//! un handler. Un hilo lee el pipe, marca el procesamiento como cancelado y recuerda qué señal
+//! llegó, para terminar con el código de salida que le corresponde.
+
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::FromRawFd;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::thread;
+
+/// Indica si se recibió una señal de cancelación.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Número de la primera señal recibida, o 0 si no se recibió ninguna.
+static SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+/// Extremo de escritura del pipe por el que el handler avisa las señales.
+static PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+/// Código de salida utilizado cuando el resultado es parcial sin que se haya recibido una
+/// señal, por ejemplo porque no se pudo abrir un archivo. No coincide con ningún 128 + señal.
+pub const INCOMPLETE_EXIT_CODE: i32 = 2;
+
+/// Señales que cancelan el procesamiento.
+const SIGNALS: [libc::c_int; 2] = [libc::SIGINT, libc::SIGTERM];
+
+/// Handler de las señales: escribe el número de señal en el pipe.
+///
+/// # Arguments
+///
+/// * `signal` - Número de la señal recibida.
+extern "C" fn on_signal(signal: libc::c_int) {
+    let byte = signal as u8;
+    // SAFETY: write es async-signal-safe y el buffer es una variable local.
+    unsafe {
+        libc::write(
+            PIPE_WRITE.load(Ordering::Relaxed),
+            &byte as *const u8 as *const libc::c_void,
+            1,
+        );
+    }
+}
+
+/// Lee las señales que avisa el handler. La primera marca el procesamiento como cancelado
+/// y una segunda termina el proceso.
+///
+/// # Arguments
+///
+/// * `pipe` - Extremo de lectura del pipe.
+fn watch_signals(mut pipe: File) {
+    let mut byte = [0u8; 1];
+    while pipe.read_exact(&mut byte).is_ok() {
+        let signal = byte[0] as i32;
+        if CANCELLED.load(Ordering::SeqCst) {
+            eprintln!("Segunda señal recibida, abortando");
+            process::exit(partial_exit_code());
+        }
+        SIGNAL.store(signal, Ordering::SeqCst);
+        CANCELLED.store(true, Ordering::SeqCst);
+        eprintln!("Señal recibida, finalizando con resultados parciales...");
+    }
+}
+
+/// Instala el handler de SIGINT/SIGTERM.
+///
+/// La primera señal marca el procesamiento como cancelado para que los workers dejen de
+/// leer líneas y se escriba un resultado parcial. Una segunda señal termina el proceso.
+pub fn install_handler() {
+    let mut fds = [0 as libc::c_int; 2];
+    // SAFETY: fds tiene lugar para los dos descriptores que devuelve pipe.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        eprintln!(
+            "Error al instalar el handler de señales: {}",
+            std::io::Error::last_os_error()
+        );
+        return;
+    }
+    PIPE_WRITE.store(fds[1], Ordering::SeqCst);
+    // SAFETY: el descriptor de lectura se acaba de crear y solo lo usa este File.
+    let pipe = unsafe { File::from_raw_fd(fds[0]) };
+    thread::spawn(move || watch_signals(pipe));
+
+    for signal in SIGNALS {
+        // SAFETY: on_signal solo hace operaciones async-signal-safe.
+        let previous =
+            unsafe { libc::signal(signal, on_signal as *const () as libc::sighandler_t) };
+        if previous == libc::SIG_ERR {
+            eprintln!(
+                "Error al instalar el handler de señales: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+/// Indica si el procesamiento fue cancelado.
+///
+/// # Returns
+///
+/// `true` si se recibió una señal de cancelación.
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
+
+/// Devuelve el código de salida de un procesamiento con resultado parcial: 128 más el número
+/// de la señal recibida (130 ante SIGINT, 143 ante SIGTERM), o `INCOMPLETE_EXIT_CODE` si no
+/// se recibió ninguna.
+pub fn partial_exit_code() -> i32 {
+    match SIGNAL.load(Ordering::SeqCst) {
+        0 => INCOMPLETE_EXIT_CODE,
+        signal => 128 + signal,
+    }
+}
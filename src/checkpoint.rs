@@ -0,0 +1,183 @@
+//! Este módulo persiste y recupera checkpoints con el `ResultData` parcial de cada archivo.
+
+use crate::output::write_atomic;
+use crate::structs::ResultData;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Tamaño del buffer utilizado para calcular el hash de un archivo.
+const HASH_BUFFER_SIZE: usize = 1 << 20;
+
+/// Cantidad de bytes del hash de la ruta que se usan en el nombre de cada checkpoint.
+const PATH_HASH_BYTES: usize = 8;
+
+/// Fingerprint: Identifica la versión de un archivo de entrada por su tamaño y fecha de
+/// modificación, sin leer su contenido.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub(crate) struct Fingerprint {
+    pub(crate) size: u64,
+    pub(crate) mtime_ns: u64,
+}
+
+/// Checkpoint: Resultado parcial de un archivo junto con el fingerprint y el hash SHA-256 del
+/// archivo.
+#[derive(Debug, Deserialize)]
+struct Checkpoint {
+    fingerprint: Fingerprint,
+    hash: String,
+    data: ResultData,
+}
+
+/// CheckpointRef: Versión prestada de `Checkpoint` utilizada para guardarlo.
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    fingerprint: &'a Fingerprint,
+    hash: &'a str,
+    data: &'a ResultData,
+}
+
+/// Calcula el hash SHA-256 del contenido de un archivo.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+///
+/// # Returns
+///
+/// El hash en hexadecimal.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer el archivo.
+pub(crate) fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
+
+/// Calcula el fingerprint de un archivo.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+///
+/// # Returns
+///
+/// El `Fingerprint` con el tamaño y la fecha de modificación del archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer la metadata del archivo.
+pub(crate) fn fingerprint(path: &Path) -> io::Result<Fingerprint> {
+    let metadata = fs::metadata(path)?;
+    let mtime_ns = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    Ok(Fingerprint {
+        size: metadata.len(),
+        mtime_ns,
+    })
+}
+
+/// Devuelve la ruta del checkpoint de un archivo de entrada. El nombre incluye un hash de la
+/// ruta absoluta, así dos archivos con el mismo nombre en distintos directorios no comparten
+/// checkpoint.
+///
+/// # Arguments
+///
+/// * `state_dir` - Directorio de estado.
+/// * `path` - Ruta del archivo de entrada.
+fn checkpoint_path(state_dir: &Path, path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let full_path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let path_hash: String = Sha256::digest(full_path.as_os_str().as_encoded_bytes())
+        [..PATH_HASH_BYTES]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    state_dir.join(format!("{}-{}.checkpoint.json", file_name, path_hash))
+}
+
+/// Carga el checkpoint de un archivo si coincide con su tamaño, fecha de modificación y hash
+/// actuales. Un archivo reescrito con el mismo tamaño dentro de la resolución de la fecha de
+/// modificación se detecta por el hash.
+///
+/// # Arguments
+///
+/// * `state_dir` - Directorio de estado.
+/// * `path` - Ruta del archivo de entrada.
+/// * `fingerprint` - Fingerprint actual del archivo.
+/// * `hash` - Hash SHA-256 actual del archivo.
+///
+/// # Returns
+///
+/// El `ResultData` guardado, o `None` si no hay checkpoint válido.
+pub(crate) fn load(
+    state_dir: &Path,
+    path: &Path,
+    fingerprint: &Fingerprint,
+    hash: &str,
+) -> Option<ResultData> {
+    let contents = fs::read(checkpoint_path(state_dir, path)).ok()?;
+    match serde_json::from_slice::<Checkpoint>(&contents) {
+        Ok(checkpoint) if checkpoint.fingerprint == *fingerprint && checkpoint.hash == hash => {
+            Some(checkpoint.data)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            eprintln!("Checkpoint inválido para {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Guarda el checkpoint de un archivo procesado por completo.
+///
+/// # Arguments
+///
+/// * `state_dir` - Directorio de estado.
+/// * `path` - Ruta del archivo de entrada.
+/// * `fingerprint` - Fingerprint del archivo procesado.
+/// * `hash` - Hash SHA-256 del archivo procesado.
+/// * `data` - `ResultData` del archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede escribir el checkpoint.
+pub(crate) fn save(
+    state_dir: &Path,
+    path: &Path,
+    fingerprint: &Fingerprint,
+    hash: &str,
+    data: &ResultData,
+) -> io::Result<()> {
+    let checkpoint = CheckpointRef {
+        fingerprint,
+        hash,
+        data,
+    };
+    let contents = serde_json::to_vec(&checkpoint).map_err(io::Error::other)?;
+    fs::create_dir_all(state_dir)?;
+    write_atomic(&checkpoint_path(state_dir, path), &contents)
+}
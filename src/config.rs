@@ -0,0 +1,506 @@
+//! Este módulo contiene la configuración del programa y el parseo de los argumentos.
+
+use crate::distributed::HEARTBEAT_INTERVAL;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Tamaño de chunk por defecto: los archivos más grandes se dividen en rangos de este tamaño.
+pub const DEFAULT_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Bytes por MB.
+const MB: u64 = 1024 * 1024;
+
+/// Uso del programa, se muestra ante argumentos erróneos.
+pub const USAGE: &str =
+    "Uso: [watch | bench | coordinator | worker | plan | merge] <workers> [--io-workers <n>] \
+[--input <dir>]... [--output <archivo>] [--state-dir <dir>] [--resume] [--cache-dir <dir>] \
+[--progress] [--report <archivo>] [--chunk-size <MB>] [--no-mmap] [--parser <serde | simd>] \
+[--schedule <largest | input>] [--backend <rayon | pipeline | threads | actors | sharded | async>] \
+[--shards <n>] [--memory-budget <MB>] \
+[--sample <archivos>] [--repeat <corridas>] [--csv <archivo>] \
+[--listen <dirección>] [--connect <dirección>] [--worker-timeout <segundos>] \
+[--task-timeout <segundos>] [--processes <n>] [--manifest <archivo> --shard <i/N>] \
+[--part <archivo>]...";
+
+/// Command: Subcomando a ejecutar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Command {
+    /// Procesa los archivos una vez e imprime el resultado.
+    #[default]
+    Run,
+    /// Procesa los archivos y mantiene el resultado actualizado ante cambios.
+    Watch,
+    /// Mide el tiempo del procesamiento con distintas cantidades de workers.
+    Bench,
+    /// Reparte los archivos entre workers conectados por TCP y combina sus resultados.
+    Coordinator,
+    /// Procesa los archivos que le asigna un coordinador.
+    Worker,
+    /// Genera el manifest que reparte los archivos en shards balanceados por tamaño.
+    Plan,
+    /// Combina los resultados de varios shards.
+    Merge,
+    /// Subcomando interno de `--processes`: procesa los archivos que recibe por stdin y
+    /// escribe sus resultados por stdout.
+    Child,
+}
+
+/// Parser: Implementación con la que se parsea cada línea JSON.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Parser {
+    /// serde_json.
+    #[default]
+    Serde,
+    /// simd-json. Requiere compilar con la feature `simd`.
+    Simd,
+}
+
+/// Schedule: Orden en el que los workers toman los archivos de entrada.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    /// Primero los archivos más grandes, para que no quede uno grande al final.
+    #[default]
+    Largest,
+    /// En el orden del listado, repartidos por rayon.
+    Input,
+}
+
+/// Backend: Implementación con la que se reparte el procesamiento entre los workers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// ThreadPool de rayon, con paralelismo por archivo y dentro de cada archivo.
+    #[default]
+    Rayon,
+    /// Un lector, un pool de parsers y un agregador conectados por canales acotados.
+    Pipeline,
+    /// Hilos de la biblioteca estándar que toman rangos de una cola con `Mutex` y envían sus
+    /// resultados por `mpsc`.
+    Threads,
+    /// Actores lectores, parsers y agregadores por sitio que se comunican solo por mensajes.
+    Actors,
+    /// Workers de rayon que suman cada línea en un mapa compartido dividido en shards con un
+    /// `Mutex` cada uno.
+    Sharded,
+    /// Tareas de tokio que leen varios archivos a la vez y entregan lotes de líneas al pool de
+    /// parseo de rayon.
+    Async,
+}
+
+/// Config: Contiene las opciones con las que se ejecuta el procesamiento.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Subcomando a ejecutar.
+    pub command: Command,
+    /// Cantidad de workers del ThreadPool de parseo. En `bench` es la cantidad máxima a medir y
+    /// en `plan`, la cantidad de shards.
+    pub workers: usize,
+    /// Cantidad de workers del ThreadPool de lectura. Si no se indica, es igual a `workers`.
+    pub io_workers: Option<usize>,
+    /// Directorio donde se guardan los checkpoints por archivo.
+    pub state_dir: Option<PathBuf>,
+    /// Si se reutilizan los checkpoints que coinciden con el archivo actual.
+    pub resume: bool,
+    /// Directorio del cache de resultados parciales por contenido de archivo.
+    pub cache_dir: Option<PathBuf>,
+    /// Directorios de entrada. Si está vacío se utiliza `data` dentro del proyecto.
+    pub inputs: Vec<PathBuf>,
+    /// Archivo donde se escribe el resultado. Si no se indica, se imprime por stdout.
+    pub output: Option<PathBuf>,
+    /// Si se muestra el progreso del procesamiento por stderr.
+    pub progress: bool,
+    /// Archivo donde se escribe el reporte de performance.
+    pub report: Option<PathBuf>,
+    /// Tamaño en bytes a partir del cual un archivo se divide en rangos que se procesan en
+    /// paralelo. Si no se indica, se usa `DEFAULT_CHUNK_SIZE`.
+    pub chunk_size: Option<u64>,
+    /// Si se desactiva la lectura de archivos regulares mapeados en memoria.
+    pub no_mmap: bool,
+    /// Implementación con la que se parsea cada línea.
+    pub parser: Parser,
+    /// Orden en el que se procesan los archivos.
+    pub schedule: Schedule,
+    /// Implementación con la que se reparte el procesamiento.
+    pub backend: Backend,
+    /// Cantidad de shards del mapa compartido de `--backend sharded`.
+    pub shards: Option<usize>,
+    /// Cantidad máxima de bytes de líneas leídas y todavía no procesadas. Si no se indica, no
+    /// hay límite.
+    pub memory_budget: Option<u64>,
+    /// Cantidad de archivos de entrada que se usan en `bench`. Por defecto, todos.
+    pub sample: Option<usize>,
+    /// Cantidad de corridas por configuración en `bench`. Se toma el menor tiempo.
+    pub repeat: Option<usize>,
+    /// Archivo CSV donde `bench` escribe las mediciones.
+    pub csv: Option<PathBuf>,
+    /// Dirección donde `coordinator` espera a los workers.
+    pub listen: Option<String>,
+    /// Dirección del coordinador al que se conecta `worker`.
+    pub connect: Option<String>,
+    /// Tiempo sin mensajes de un worker ocupado tras el cual `coordinator` lo da por caído.
+    pub worker_timeout: Option<Duration>,
+    /// Tiempo tras el cual `coordinator` asigna un archivo en proceso también a otro worker.
+    pub task_timeout: Option<Duration>,
+    /// Cantidad de procesos hijos entre los que se reparten los archivos. Cada uno usa
+    /// `workers` workers.
+    pub processes: Option<usize>,
+    /// Manifest generado con `plan`, del que se toma el shard a procesar.
+    pub manifest: Option<PathBuf>,
+    /// Shard a procesar, desde 0, y cantidad total de shards.
+    pub shard: Option<(usize, usize)>,
+    /// Resultados de shards que combina `merge`.
+    pub parts: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Parsea los argumentos del programa (sin incluir el nombre del ejecutable).
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Argumentos del programa.
+    ///
+    /// # Returns
+    ///
+    /// La `Config` correspondiente o un mensaje de error.
+    pub fn from_args(args: &[String]) -> Result<Config, String> {
+        let mut args = args.iter().peekable();
+        let command = match args.peek().map(|arg| arg.as_str()) {
+            Some("watch") => {
+                args.next();
+                Command::Watch
+            }
+            Some("bench") => {
+                args.next();
+                Command::Bench
+            }
+            Some("coordinator") => {
+                args.next();
+                Command::Coordinator
+            }
+            Some("worker") => {
+                args.next();
+                Command::Worker
+            }
+            Some("plan") => {
+                args.next();
+                Command::Plan
+            }
+            Some("merge") => {
+                args.next();
+                Command::Merge
+            }
+            Some("child") => {
+                args.next();
+                Command::Child
+            }
+            _ => Command::Run,
+        };
+        let mut config = Config {
+            command,
+            workers: parse_workers(args.next())?,
+            ..Config::default()
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--io-workers" => config.io_workers = Some(parse_count(arg, args.next())?),
+                "--state-dir" => {
+                    config.state_dir = Some(PathBuf::from(flag_value(arg, args.next())?))
+                }
+                "--resume" => config.resume = true,
+                "--progress" => config.progress = true,
+                "--report" => config.report = Some(PathBuf::from(flag_value(arg, args.next())?)),
+                "--cache-dir" => {
+                    config.cache_dir = Some(PathBuf::from(flag_value(arg, args.next())?))
+                }
+                "--input" => config
+                    .inputs
+                    .push(PathBuf::from(flag_value(arg, args.next())?)),
+                "--output" => config.output = Some(PathBuf::from(flag_value(arg, args.next())?)),
+                "--chunk-size" => config.chunk_size = Some(parse_megabytes(arg, args.next())?),
+                "--no-mmap" => config.no_mmap = true,
+                "--parser" => config.parser = parse_parser(flag_value(arg, args.next())?)?,
+                "--backend" => config.backend = parse_backend(flag_value(arg, args.next())?)?,
+                "--memory-budget" => {
+                    config.memory_budget = Some(parse_megabytes(arg, args.next())?)
+                }
+                "--shards" => config.shards = Some(parse_count(arg, args.next())?),
+                "--schedule" => config.schedule = parse_schedule(flag_value(arg, args.next())?)?,
+                "--sample" => config.sample = Some(parse_count(arg, args.next())?),
+                "--repeat" => config.repeat = Some(parse_count(arg, args.next())?),
+                "--csv" => config.csv = Some(PathBuf::from(flag_value(arg, args.next())?)),
+                "--listen" => config.listen = Some(flag_value(arg, args.next())?.to_string()),
+                "--connect" => config.connect = Some(flag_value(arg, args.next())?.to_string()),
+                "--worker-timeout" => {
+                    config.worker_timeout = Some(parse_seconds(arg, args.next())?)
+                }
+                "--task-timeout" => config.task_timeout = Some(parse_seconds(arg, args.next())?),
+                "--processes" => config.processes = Some(parse_count(arg, args.next())?),
+                "--manifest" => {
+                    config.manifest = Some(PathBuf::from(flag_value(arg, args.next())?))
+                }
+                "--shard" => config.shard = Some(parse_shard(flag_value(arg, args.next())?)?),
+                "--part" => config
+                    .parts
+                    .push(PathBuf::from(flag_value(arg, args.next())?)),
+                _ => return Err(format!("Argumento desconocido: {}", arg)),
+            }
+        }
+
+        if config.resume && config.state_dir.is_none() {
+            return Err("--resume requiere --state-dir".to_string());
+        }
+        if config.backend != Backend::Rayon
+            && (config.state_dir.is_some() || config.cache_dir.is_some() || config.progress)
+        {
+            return Err(
+                "--state-dir, --cache-dir y --progress solo están disponibles con --backend rayon"
+                    .to_string(),
+            );
+        }
+        if config.memory_budget.is_some() && config.backend != Backend::Rayon {
+            return Err("--memory-budget solo está disponible con --backend rayon".to_string());
+        }
+        if config.shards.is_some() && config.backend != Backend::Sharded {
+            return Err("--shards requiere --backend sharded".to_string());
+        }
+        if config.command == Command::Watch && config.output.is_none() {
+            return Err("watch requiere --output".to_string());
+        }
+        if (config.command == Command::Coordinator) != config.listen.is_some() {
+            return Err(
+                "--listen solo está disponible, y es obligatorio, en coordinator".to_string(),
+            );
+        }
+        if config.manifest.is_some() != config.shard.is_some() {
+            return Err("--manifest y --shard deben indicarse juntos".to_string());
+        }
+        if config.shard.is_some() {
+            if config.command != Command::Run || config.backend != Backend::Rayon {
+                return Err(
+                    "--shard solo está disponible en una corrida con --backend rayon".to_string(),
+                );
+            }
+            if config.processes.is_some() || config.progress || config.report.is_some() {
+                return Err("--shard no admite --processes, --progress ni --report".to_string());
+            }
+        }
+        if (config.command == Command::Merge) == config.parts.is_empty() {
+            return Err("--part solo está disponible, y es obligatorio, en merge".to_string());
+        }
+        if config.processes.is_some() {
+            if config.command != Command::Run || config.backend != Backend::Rayon {
+                return Err(
+                    "--processes solo está disponible en una corrida con --backend rayon"
+                        .to_string(),
+                );
+            }
+            if config.progress || config.report.is_some() {
+                return Err("--processes no admite --progress ni --report".to_string());
+            }
+        }
+        if config.command != Command::Coordinator
+            && (config.worker_timeout.is_some() || config.task_timeout.is_some())
+        {
+            return Err(
+                "--worker-timeout y --task-timeout solo están disponibles en coordinator"
+                    .to_string(),
+            );
+        }
+        if config
+            .worker_timeout
+            .is_some_and(|timeout| timeout <= HEARTBEAT_INTERVAL)
+        {
+            return Err(format!(
+                "--worker-timeout debe superar el intervalo de heartbeats ({:?})",
+                HEARTBEAT_INTERVAL
+            ));
+        }
+        if (config.command == Command::Worker) != config.connect.is_some() {
+            return Err("--connect solo está disponible, y es obligatorio, en worker".to_string());
+        }
+        if matches!(config.command, Command::Coordinator | Command::Worker)
+            && config.backend != Backend::Rayon
+        {
+            return Err(
+                "coordinator y worker solo están disponibles con --backend rayon".to_string(),
+            );
+        }
+        Ok(config)
+    }
+
+    /// Devuelve la cantidad de workers del ThreadPool de lectura.
+    pub fn io_workers(&self) -> usize {
+        self.io_workers.unwrap_or(self.workers)
+    }
+
+    /// Devuelve el tamaño a partir del cual un archivo se divide en rangos.
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE)
+    }
+}
+
+/// Parsea la cantidad de workers.
+///
+/// # Arguments
+///
+/// * `arg` - Argumento con la cantidad de workers.
+///
+/// # Returns
+///
+/// La cantidad de workers o un mensaje de error.
+fn parse_workers(arg: Option<&String>) -> Result<usize, String> {
+    let arg = arg.ok_or("Debe ingresar como argumento la cantidad de workers deseada")?;
+    match arg.parse() {
+        Ok(workers) if workers > 0 => Ok(workers),
+        _ => Err(format!(
+            "Se requiere un número entero positivo de workers: {}",
+            arg
+        )),
+    }
+}
+
+/// Parsea la implementación del parser JSON.
+///
+/// # Arguments
+///
+/// * `value` - Valor del flag `--parser`.
+///
+/// # Returns
+///
+/// El `Parser` correspondiente o un mensaje de error.
+fn parse_parser(value: &str) -> Result<Parser, String> {
+    match value {
+        "serde" => Ok(Parser::Serde),
+        "simd" if cfg!(feature = "simd") => Ok(Parser::Simd),
+        "simd" => Err("--parser simd requiere compilar con --features simd".to_string()),
+        _ => Err(format!("Parser desconocido: {}", value)),
+    }
+}
+
+/// Parsea la implementación con la que se reparte el procesamiento.
+///
+/// # Arguments
+///
+/// * `value` - Valor del flag `--backend`.
+///
+/// # Returns
+///
+/// El `Backend` correspondiente o un mensaje de error.
+fn parse_backend(value: &str) -> Result<Backend, String> {
+    match value {
+        "rayon" => Ok(Backend::Rayon),
+        "pipeline" => Ok(Backend::Pipeline),
+        "threads" => Ok(Backend::Threads),
+        "actors" => Ok(Backend::Actors),
+        "sharded" => Ok(Backend::Sharded),
+        "async" => Ok(Backend::Async),
+        _ => Err(format!("Backend desconocido: {}", value)),
+    }
+}
+
+/// Parsea el orden de procesamiento de los archivos.
+///
+/// # Arguments
+///
+/// * `value` - Valor del flag `--schedule`.
+///
+/// # Returns
+///
+/// El `Schedule` correspondiente o un mensaje de error.
+fn parse_schedule(value: &str) -> Result<Schedule, String> {
+    match value {
+        "largest" => Ok(Schedule::Largest),
+        "input" => Ok(Schedule::Input),
+        _ => Err(format!("Orden desconocido: {}", value)),
+    }
+}
+
+/// Parsea el shard a procesar, con el formato `i/N` e `i` desde 0.
+///
+/// # Arguments
+///
+/// * `value` - Valor del flag `--shard`.
+///
+/// # Returns
+///
+/// El shard y la cantidad de shards, o un mensaje de error.
+fn parse_shard(value: &str) -> Result<(usize, usize), String> {
+    let parsed = value
+        .split_once('/')
+        .and_then(|(index, count)| Some((index.parse().ok()?, count.parse().ok()?)));
+    match parsed {
+        Some((index, count)) if index < count => Ok((index, count)),
+        _ => Err(format!(
+            "--shard requiere el formato i/N con 0 <= i < N: {}",
+            value
+        )),
+    }
+}
+
+/// Parsea una cantidad de segundos positiva.
+///
+/// # Arguments
+///
+/// * `flag` - Nombre del flag.
+/// * `value` - Siguiente argumento, si existe.
+///
+/// # Returns
+///
+/// La duración indicada o un mensaje de error.
+fn parse_seconds(flag: &str, value: Option<&String>) -> Result<Duration, String> {
+    Ok(Duration::from_secs(parse_count(flag, value)? as u64))
+}
+
+/// Parsea una cantidad positiva de MB y la convierte en bytes.
+///
+/// # Arguments
+///
+/// * `flag` - Nombre del flag.
+/// * `value` - Siguiente argumento, si existe.
+///
+/// # Returns
+///
+/// La cantidad de bytes o un mensaje de error si el valor no entra en un `u64`.
+fn parse_megabytes(flag: &str, value: Option<&String>) -> Result<u64, String> {
+    let megabytes = parse_count(flag, value)?;
+    (megabytes as u64)
+        .checked_mul(MB)
+        .ok_or_else(|| format!("{} es demasiado grande: {} MB", flag, megabytes))
+}
+
+/// Parsea el valor entero positivo de un flag.
+///
+/// # Arguments
+///
+/// * `flag` - Nombre del flag.
+/// * `value` - Siguiente argumento, si existe.
+///
+/// # Returns
+///
+/// El valor del flag o un mensaje de error.
+fn parse_count(flag: &str, value: Option<&String>) -> Result<usize, String> {
+    let value = flag_value(flag, value)?;
+    match value.parse() {
+        Ok(count) if count > 0 => Ok(count),
+        _ => Err(format!("{} requiere un entero positivo: {}", flag, value)),
+    }
+}
+
+/// Obtiene el valor de un flag.
+///
+/// # Arguments
+///
+/// * `flag` - Nombre del flag.
+/// * `value` - Siguiente argumento, si existe.
+///
+/// # Returns
+///
+/// El valor del flag o un mensaje de error si falta.
+fn flag_value<'a>(flag: &str, value: Option<&'a String>) -> Result<&'a str, String> {
+    value
+        .map(|v| v.as_str())
+        .ok_or_else(|| format!("Falta el valor de {}", flag))
+}
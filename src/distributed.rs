@@ -0,0 +1,741 @@
+//! Este módulo implementa el modo distribuido, con los subcomandos `coordinator` y `worker`.
+//!
+//! El coordinador lista los archivos de entrada y se los asigna de a uno a los workers que se
+//! conectan por TCP. Cada worker procesa el archivo asignado y devuelve su resultado parcial
+//! serializado con bincode. El coordinador combina los resultados con `Add` y calcula los
+//! totals. Las rutas se envían absolutas, y los workers deben poder leer los archivos en esas
+//! mismas rutas. Un worker que no puede abrir un archivo lo informa como no procesado, sin
+//! terminar.
+//!
+//! Mientras procesan un archivo los workers envían heartbeats. El coordinador reasigna los
+//! archivos de los workers que se desconectan o dejan de responder, y los que vuelven con un
+//! resultado incompleto, asigna también a otro worker los que tardan demasiado y se queda con
+//! el primer resultado completo de cada archivo.
+
+use crate::budget::MemoryBudget;
+use crate::cancel::{is_cancelled, partial_exit_code};
+use crate::config::Config;
+use crate::intern::Session;
+use crate::output::write_result;
+use crate::pools::Pools;
+use crate::processors::{
+    absolute_paths, incomplete_data, input_files, merge_results, not_processed, process_totals,
+    try_process_file_with_state, PADRON,
+};
+use crate::structs::{
+    Accumulator, FileResult, FileSource, FileStats, FileStatus, ResultData, SiteData, TagData,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Tamaño máximo de un mensaje, para no reservar memoria de más ante datos corruptos.
+const MAX_FRAME_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Intervalo con el que se revisa si hay conexiones nuevas o trabajo pendiente.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Intervalo con el que un worker ocupado avisa al coordinador que sigue vivo.
+pub(crate) const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tiempo por defecto sin mensajes de un worker ocupado antes de darlo por caído.
+const DEFAULT_WORKER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Plazo por defecto para procesar un archivo antes de asignarlo también a otro worker.
+const DEFAULT_TASK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Cantidad de intentos de conexión de un worker, por si el coordinador todavía no inició.
+const CONNECT_ATTEMPTS: usize = 50;
+
+/// Cantidad máxima de resultados incompletos de un archivo. Al llegar a este límite el
+/// coordinador acepta el último en lugar de reasignar el archivo otra vez.
+pub(crate) const MAX_ATTEMPTS: usize = 3;
+
+/// ToWorker: Mensaje del coordinador a un worker.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum ToWorker {
+    /// Procesar el archivo `path`, que ocupa la posición `index` en la lista del coordinador.
+    Assign { index: usize, path: PathBuf },
+    /// No quedan archivos: el worker termina.
+    Finish,
+}
+
+/// ToCoordinator: Mensaje de un worker al coordinador.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum ToCoordinator {
+    /// Resultado del archivo asignado.
+    Done { index: usize, result: RemoteResult },
+    /// El worker sigue procesando el archivo asignado.
+    Heartbeat,
+}
+
+/// RemoteResult: Resultado parcial de un archivo tal como se envía entre procesos. La ruta no
+/// se envía porque quien recibe el resultado ya la conoce.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RemoteResult {
+    status: FileStatus,
+    source: FileSource,
+    stats: FileStats,
+    sites: HashMap<String, SiteData>,
+    tags: HashMap<String, TagData>,
+}
+
+impl From<FileResult> for RemoteResult {
+    /// Resuelve los nombres del resultado, porque los identificadores del interner solo son
+    /// válidos dentro del proceso.
+    fn from(result: FileResult) -> Self {
+        let data = result.data.into_result_data(PADRON);
+        RemoteResult {
+            status: result.status,
+            source: result.source,
+            stats: result.stats,
+            sites: data.sites,
+            tags: data.tags,
+        }
+    }
+}
+
+impl RemoteResult {
+    /// Reconstruye el `FileResult` recibido, internando sus nombres. Se tiene que llamar
+    /// dentro de una `Session`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Ruta del archivo.
+    pub(crate) fn into_file_result(self, path: &Path) -> FileResult {
+        FileResult {
+            path: path.to_path_buf(),
+            status: self.status,
+            source: self.source,
+            stats: self.stats,
+            data: Accumulator::from_result_data(ResultData::new(PADRON, self.sites, self.tags)),
+        }
+    }
+}
+
+/// Escribe un mensaje precedido por su longitud.
+///
+/// # Arguments
+///
+/// * `writer` - Destino del mensaje.
+/// * `message` - Mensaje a escribir.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede serializar o escribir el mensaje.
+pub(crate) fn write_frame<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    let contents = bincode::serialize(message).map_err(io::Error::other)?;
+    writer.write_all(&(contents.len() as u64).to_le_bytes())?;
+    writer.write_all(&contents)?;
+    writer.flush()
+}
+
+/// Lee un mensaje escrito con `write_frame`.
+///
+/// # Arguments
+///
+/// * `reader` - Origen del mensaje.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer o deserializar el mensaje.
+pub(crate) fn read_frame<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<T> {
+    let mut length = [0; 8];
+    reader.read_exact(&mut length)?;
+    let length = u64::from_le_bytes(length);
+    if length > MAX_FRAME_BYTES {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Mensaje demasiado grande: {} bytes", length),
+        ));
+    }
+    let mut contents = vec![0; length as usize];
+    reader.read_exact(&mut contents)?;
+    bincode::deserialize(&contents).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Timeouts: Plazos con los que el coordinador detecta workers caídos o lentos.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timeouts {
+    /// Tiempo máximo sin mensajes de un worker ocupado antes de darlo por caído.
+    pub(crate) worker: Duration,
+    /// Tiempo a partir del cual un archivo en proceso se asigna también a un worker libre.
+    pub(crate) task: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            worker: DEFAULT_WORKER_TIMEOUT,
+            task: DEFAULT_TASK_TIMEOUT,
+        }
+    }
+}
+
+impl Timeouts {
+    /// Toma los plazos de la configuración, con los valores por defecto si no se indican.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuración del coordinador.
+    pub(crate) fn from_config(config: &Config) -> Self {
+        Timeouts {
+            worker: config.worker_timeout.unwrap_or(DEFAULT_WORKER_TIMEOUT),
+            task: config.task_timeout.unwrap_or(DEFAULT_TASK_TIMEOUT),
+        }
+    }
+}
+
+/// Retries: Reintentos realizados durante una corrida distribuida.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Retries {
+    /// Archivos reasignados porque se perdió la conexión con su worker.
+    pub(crate) lost: usize,
+    /// Archivos reasignados porque su worker dejó de enviar heartbeats.
+    pub(crate) timed_out: usize,
+    /// Archivos asignados también a otro worker por superar el plazo de procesamiento.
+    pub(crate) slow: usize,
+    /// Archivos reasignados porque su worker devolvió un resultado incompleto.
+    pub(crate) incomplete: usize,
+    /// Resultados descartados porque el archivo ya tenía resultado.
+    pub(crate) duplicates: usize,
+}
+
+impl Retries {
+    /// Devuelve la cantidad total de reasignaciones.
+    pub(crate) fn reassigned(&self) -> usize {
+        self.lost + self.timed_out + self.slow + self.incomplete
+    }
+}
+
+/// Assignments: Archivos pendientes y resultados recibidos por el coordinador.
+struct Assignments {
+    state: Mutex<AssignmentState>,
+    changed: Condvar,
+    timeouts: Timeouts,
+}
+
+/// AssignmentState: Estado de la asignación de archivos.
+struct AssignmentState {
+    pending: VecDeque<usize>,
+    results: Vec<Option<FileResult>>,
+    /// Cantidad de workers que están procesando cada archivo.
+    running: Vec<usize>,
+    /// Momento de la última asignación de cada archivo.
+    assigned: Vec<Option<Instant>>,
+    /// Cantidad de resultados incompletos recibidos de cada archivo.
+    attempts: Vec<usize>,
+    /// Último resultado incompleto de cada archivo, que se usa si no llega uno completo.
+    fallback: Vec<Option<FileResult>>,
+    remaining: usize,
+    retries: Retries,
+}
+
+impl AssignmentState {
+    /// Busca el archivo en proceso asignado hace más tiempo, si superó el plazo.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Plazo de procesamiento de un archivo.
+    fn overdue(&self, timeout: Duration) -> Option<usize> {
+        (0..self.results.len())
+            .filter(|&index| self.results[index].is_none() && self.running[index] > 0)
+            .filter_map(|index| Some((index, self.assigned[index]?)))
+            .filter(|(_, assigned)| assigned.elapsed() >= timeout)
+            .min_by_key(|(_, assigned)| *assigned)
+            .map(|(index, _)| index)
+    }
+}
+
+impl Assignments {
+    /// Crea la asignación con todos los archivos pendientes.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Cantidad de archivos.
+    /// * `timeouts` - Plazos para detectar workers caídos o lentos.
+    fn new(count: usize, timeouts: Timeouts) -> Self {
+        Assignments {
+            state: Mutex::new(AssignmentState {
+                pending: (0..count).collect(),
+                results: (0..count).map(|_| None).collect(),
+                running: vec![0; count],
+                assigned: vec![None; count],
+                attempts: vec![0; count],
+                fallback: (0..count).map(|_| None).collect(),
+                remaining: count,
+                retries: Retries::default(),
+            }),
+            changed: Condvar::new(),
+            timeouts,
+        }
+    }
+
+    /// Toma el lock del estado.
+    fn lock(&self) -> MutexGuard<'_, AssignmentState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Devuelve el próximo archivo a asignar. Si no hay pendientes, asigna de nuevo el archivo
+    /// en proceso que superó el plazo, o espera a que alguno se vuelva a encolar.
+    ///
+    /// # Returns
+    ///
+    /// La posición del archivo, o `None` si ya no quedan archivos o se canceló.
+    fn next(&self) -> Option<usize> {
+        let mut state = self.lock();
+        loop {
+            if state.remaining == 0 || is_cancelled() {
+                return None;
+            }
+            let mut next = state.pending.pop_front();
+            if next.is_none() {
+                next = state.overdue(self.timeouts.task);
+                state.retries.slow += next.is_some() as usize;
+            }
+            if let Some(index) = next {
+                state.running[index] += 1;
+                state.assigned[index] = Some(Instant::now());
+                return Some(index);
+            }
+            state = self
+                .changed
+                .wait_timeout(state, POLL_INTERVAL)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Registra el resultado de un archivo. Si el archivo ya tenía resultado, por haberse
+    /// asignado a más de un worker, el nuevo se descarta.
+    ///
+    /// Un resultado incompleto (por ejemplo, de un worker que se canceló o no pudo abrir el
+    /// archivo) se guarda aparte y el archivo se vuelve a encolar si ningún otro worker lo está
+    /// procesando, hasta recibir `MAX_ATTEMPTS` resultados incompletos.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `result` - Resultado recibido del worker.
+    fn complete(&self, index: usize, result: FileResult) {
+        let mut state = self.lock();
+        state.running[index] -= 1;
+        if state.results[index].is_some() {
+            state.retries.duplicates += 1;
+        } else if result.status != FileStatus::Complete && state.attempts[index] + 1 < MAX_ATTEMPTS
+        {
+            state.attempts[index] += 1;
+            state.fallback[index] = Some(result);
+            if state.running[index] == 0 {
+                state.pending.push_back(index);
+                state.retries.incomplete += 1;
+            }
+        } else {
+            state.results[index] = Some(result);
+            state.remaining -= 1;
+        }
+        self.changed.notify_all();
+    }
+
+    /// Registra que un worker dejó de procesar un archivo sin devolver su resultado. Si ningún
+    /// otro worker lo está procesando, el archivo se vuelve a encolar.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Posición del archivo.
+    /// * `timed_out` - Si el worker dejó de enviar heartbeats, en lugar de desconectarse.
+    fn fail(&self, index: usize, timed_out: bool) {
+        let mut state = self.lock();
+        state.running[index] -= 1;
+        if state.results[index].is_none() && state.running[index] == 0 {
+            state.pending.push_front(index);
+            if timed_out {
+                state.retries.timed_out += 1;
+            } else {
+                state.retries.lost += 1;
+            }
+        }
+        self.changed.notify_all();
+    }
+
+    /// Indica si ya se recibieron todos los resultados o se canceló.
+    fn finished(&self) -> bool {
+        self.lock().remaining == 0 || is_cancelled()
+    }
+}
+
+/// Espera el resultado del archivo asignado a un worker, descartando sus heartbeats.
+///
+/// # Arguments
+///
+/// * `stream` - Conexión con el worker.
+/// * `assignments` - Asignación compartida por todas las conexiones.
+///
+/// # Returns
+///
+/// La posición del archivo y su resultado, o `None` si la corrida terminó mientras tanto.
+///
+/// # Errors
+///
+/// Devuelve un error si se pierde la conexión o se vence el plazo sin recibir mensajes.
+fn receive_result(
+    stream: &mut TcpStream,
+    assignments: &Assignments,
+) -> io::Result<Option<(usize, RemoteResult)>> {
+    loop {
+        match read_frame(stream)? {
+            ToCoordinator::Heartbeat if assignments.finished() => return Ok(None),
+            ToCoordinator::Heartbeat => {}
+            ToCoordinator::Done { index, result } => return Ok(Some((index, result))),
+        }
+    }
+}
+
+/// Atiende a un worker: le asigna archivos hasta que no quedan y registra sus resultados.
+///
+/// # Arguments
+///
+/// * `stream` - Conexión con el worker.
+/// * `paths` - Archivos de entrada.
+/// * `assignments` - Asignación compartida por todas las conexiones.
+fn serve_worker(mut stream: TcpStream, paths: &[PathBuf], assignments: &Assignments) {
+    let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "desconocido".to_string(), |addr| addr.to_string());
+    let timeout = assignments.timeouts.worker;
+    if let Err(e) = stream.set_read_timeout(Some(timeout)) {
+        eprintln!(
+            "Error al configurar la conexión con el worker {}: {}",
+            peer, e
+        );
+        return;
+    }
+    while let Some(index) = assignments.next() {
+        let assign = ToWorker::Assign {
+            index,
+            path: paths[index].clone(),
+        };
+        let received = write_frame(&mut stream, &assign)
+            .and_then(|_| receive_result(&mut stream, assignments));
+        match received {
+            Ok(Some((done, result))) if done == index => {
+                assignments.complete(index, result.into_file_result(&paths[index]))
+            }
+            Ok(Some((done, _))) => {
+                eprintln!(
+                    "El worker {} respondió por el archivo {} en lugar del {}",
+                    peer, done, index
+                );
+                assignments.fail(index, false);
+                return;
+            }
+            Ok(None) => break,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                eprintln!(
+                    "El worker {} no respondió en {:?}, se reasigna {}",
+                    peer,
+                    timeout,
+                    paths[index].display()
+                );
+                assignments.fail(index, true);
+                return;
+            }
+            Err(e) => {
+                eprintln!("Se perdió la conexión con el worker {}: {}", peer, e);
+                assignments.fail(index, false);
+                return;
+            }
+        }
+    }
+    let _ = write_frame(&mut stream, &ToWorker::Finish);
+}
+
+/// Acepta workers y les reparte los archivos hasta tener el resultado de todos.
+///
+/// Los archivos de un worker que se desconecta o deja de enviar heartbeats vuelven a la cola, y
+/// los que superan el plazo de procesamiento se asignan también al próximo worker libre. De cada
+/// archivo se conserva solo el primer resultado recibido.
+///
+/// # Arguments
+///
+/// * `listener` - Socket donde se conectan los workers.
+/// * `paths` - Archivos de entrada.
+/// * `timeouts` - Plazos para detectar workers caídos o lentos.
+///
+/// # Returns
+///
+/// El resultado de cada archivo, en el orden de `paths`, y los reintentos realizados. Los
+/// archivos sin resultado completo por una cancelación quedan con su último resultado
+/// incompleto o, si no tienen ninguno, como `NotProcessed`.
+///
+/// # Errors
+///
+/// Devuelve un error si falla el socket del coordinador.
+pub(crate) fn coordinate(
+    listener: &TcpListener,
+    paths: &[PathBuf],
+    timeouts: Timeouts,
+) -> io::Result<(Vec<FileResult>, Retries)> {
+    listener.set_nonblocking(true)?;
+    let assignments = Assignments::new(paths.len(), timeouts);
+    thread::scope(|scope| {
+        while !assignments.finished() {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(false)?;
+                    let assignments = &assignments;
+                    scope.spawn(move || serve_worker(stream, paths, assignments));
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    })?;
+    let state = assignments
+        .state
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner());
+    let results = state
+        .results
+        .into_iter()
+        .zip(state.fallback)
+        .zip(paths)
+        .map(|((result, fallback), path)| {
+            result.or(fallback).unwrap_or_else(|| not_processed(path))
+        })
+        .collect();
+    Ok((results, state.retries))
+}
+
+/// Conecta un worker con el coordinador, reintentando mientras el coordinador no acepte
+/// conexiones.
+///
+/// # Arguments
+///
+/// * `address` - Dirección del coordinador.
+///
+/// # Errors
+///
+/// Devuelve el último error de conexión.
+fn connect(address: &str) -> io::Result<TcpStream> {
+    let mut attempt = 1;
+    loop {
+        match TcpStream::connect(address) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if attempt >= CONNECT_ATTEMPTS || is_cancelled() => return Err(e),
+            Err(_) => {
+                attempt += 1;
+                thread::sleep(POLL_INTERVAL * 2);
+            }
+        }
+    }
+}
+
+/// Indica si un error de la conexión se debe a que el otro extremo la cerró.
+///
+/// # Arguments
+///
+/// * `e` - Error de la conexión.
+fn closed(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        ErrorKind::UnexpectedEof
+            | ErrorKind::BrokenPipe
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+    )
+}
+
+/// Envía un mensaje al coordinador. El lock evita que un heartbeat se intercale con otro
+/// mensaje.
+///
+/// # Arguments
+///
+/// * `writer` - Conexión con el coordinador.
+/// * `message` - Mensaje a enviar.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede escribir el mensaje.
+fn send(writer: &Mutex<TcpStream>, message: &ToCoordinator) -> io::Result<()> {
+    write_frame(
+        &mut *writer.lock().unwrap_or_else(|e| e.into_inner()),
+        message,
+    )
+}
+
+/// Ejecuta `work` mientras envía heartbeats al coordinador, para que no lo dé por caído.
+///
+/// # Arguments
+///
+/// * `writer` - Conexión con el coordinador.
+/// * `work` - Trabajo a ejecutar.
+///
+/// # Returns
+///
+/// El resultado de `work`.
+fn with_heartbeats<T>(writer: &Mutex<TcpStream>, work: impl FnOnce() -> T) -> T {
+    let (stop, stopped) = channel::<()>();
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            while stopped.recv_timeout(HEARTBEAT_INTERVAL) == Err(RecvTimeoutError::Timeout) {
+                if send(writer, &ToCoordinator::Heartbeat).is_err() {
+                    break;
+                }
+            }
+        });
+        let value = work();
+        drop(stop);
+        value
+    })
+}
+
+/// Procesa los archivos que asigna el coordinador hasta que indica que no quedan más o cierra
+/// la conexión. Mientras procesa un archivo envía heartbeats. Si no puede abrir o leer un
+/// archivo, lo devuelve como no procesado.
+///
+/// # Arguments
+///
+/// * `stream` - Conexión con el coordinador.
+/// * `config` - Configuración del procesamiento del worker.
+/// * `pools` - ThreadPools del worker.
+///
+/// # Returns
+///
+/// La cantidad de archivos procesados.
+///
+/// # Errors
+///
+/// Devuelve un error si falla la conexión con el coordinador.
+pub(crate) fn serve(mut stream: TcpStream, config: &Config, pools: &Pools) -> io::Result<usize> {
+    let budget = config.memory_budget.map(MemoryBudget::new);
+    let writer = Mutex::new(stream.try_clone()?);
+    let mut processed = 0;
+    loop {
+        let message = match read_frame(&mut stream) {
+            Ok(message) => message,
+            Err(e) if closed(&e) => {
+                eprintln!("El coordinador cerró la conexión");
+                return Ok(processed);
+            }
+            Err(e) => return Err(e),
+        };
+        match message {
+            ToWorker::Assign { index, path } => {
+                let session = Session::start();
+                let result = with_heartbeats(&writer, || {
+                    try_process_file_with_state(&path, config, pools, None, budget.as_ref())
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("Error al abrir archivo {}: {}", path.display(), e);
+                    not_processed(&path)
+                });
+                let result = RemoteResult::from(result);
+                drop(session);
+                match send(&writer, &ToCoordinator::Done { index, result }) {
+                    Ok(()) => processed += 1,
+                    Err(e) if closed(&e) => {
+                        eprintln!("El coordinador cerró la conexión");
+                        return Ok(processed);
+                    }
+                    Err(e) => return Err(e),
+                }
+                if is_cancelled() {
+                    return Ok(processed);
+                }
+            }
+            ToWorker::Finish => return Ok(processed),
+        }
+    }
+}
+
+/// Ejecuta el subcomando `coordinator`: reparte los archivos entre los workers, combina sus
+/// resultados y escribe el resultado final.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del coordinador. Debe tener `listen`.
+/// * `pools` - ThreadPools con los que se combinan los resultados.
+pub fn coordinator(config: &Config, pools: &Pools) {
+    let start = Instant::now();
+    let address = config
+        .listen
+        .as_deref()
+        .expect("coordinator requiere --listen");
+    let listener = TcpListener::bind(address).unwrap_or_else(|e| {
+        eprintln!("Error al escuchar en {}: {}", address, e);
+        std::process::exit(1);
+    });
+    let paths = absolute_paths(input_files(config));
+    eprintln!(
+        "Coordinador escuchando en {} ({} archivos)",
+        listener
+            .local_addr()
+            .map_or(address.to_string(), |a| a.to_string()),
+        paths.len()
+    );
+
+    let session = Session::start();
+    let (results, retries) = coordinate(&listener, &paths, Timeouts::from_config(config))
+        .unwrap_or_else(|e| {
+            eprintln!("Error en el coordinador: {}", e);
+            std::process::exit(1);
+        });
+    let incomplete = incomplete_data(&results);
+    let mut result_data = pools.parse().install(|| {
+        let mut result_data = merge_results(results);
+        process_totals(&mut result_data);
+        result_data
+    });
+    drop(session);
+    result_data.incomplete = incomplete;
+
+    if let Err(e) = write_result(&result_data, config.output.as_deref()) {
+        eprintln!("Error al escribir el resultado: {}", e);
+        std::process::exit(1);
+    }
+    eprintln!("Tiempo transcurrido: {:?}", start.elapsed());
+    eprintln!(
+        "Reintentos: {} archivos reasignados ({} por desconexión, {} por falta de heartbeats, {} por lentitud, {} por resultado incompleto), {} resultados duplicados descartados",
+        retries.reassigned(),
+        retries.lost,
+        retries.timed_out,
+        retries.slow,
+        retries.incomplete,
+        retries.duplicates
+    );
+    if result_data.incomplete.is_some() {
+        eprintln!("Procesamiento interrumpido: el resultado es parcial");
+        std::process::exit(partial_exit_code());
+    }
+}
+
+/// Ejecuta el subcomando `worker`: se conecta al coordinador y procesa los archivos que le
+/// asigna.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del worker. Debe tener `connect`.
+/// * `pools` - ThreadPools del worker.
+pub fn worker(config: &Config, pools: &Pools) {
+    let address = config
+        .connect
+        .as_deref()
+        .expect("worker requiere --connect");
+    let processed = connect(address).and_then(|stream| serve(stream, config, pools));
+    match processed {
+        Ok(processed) => eprintln!("Worker finalizado: {} archivos procesados", processed),
+        Err(e) => {
+            eprintln!("Error en la conexión con el coordinador {}: {}", address, e);
+            std::process::exit(1);
+        }
+    }
+}
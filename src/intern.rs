@@ -0,0 +1,167 @@
+//! Este módulo asigna identificadores compactos a los nombres de sitios y etiquetas.
+//!
+//! Durante la agregación los contadores se indexan por estos identificadores, y los nombres
+//! se resuelven recién al combinar los resultados de todos los archivos, antes de calcular
+//! los totals o de serializar un resultado.
+//!
+//! Los identificadores son válidos mientras haya alguna `Session` abierta. Al cerrarse la
+//! última la tabla se vacía, para que un proceso de larga duración como `watch` no acumule
+//! los nombres de todos los archivos que vio.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex, RwLock, RwLockReadGuard};
+
+/// Identificador de un nombre internado.
+pub(crate) type Id = u32;
+
+/// Interner: Tabla de nombres internados.
+#[derive(Default)]
+struct Interner {
+    ids: HashMap<Arc<str>, Id>,
+    names: Vec<Arc<str>>,
+}
+
+/// SharedInterner: Tabla de nombres compartida por todos los workers, junto con la cantidad
+/// de sesiones abiertas. `generation` cambia cada vez que se vacía la tabla.
+#[derive(Default)]
+pub(crate) struct SharedInterner {
+    table: RwLock<Interner>,
+    sessions: Mutex<usize>,
+    generation: AtomicU64,
+}
+
+impl SharedInterner {
+    /// Registra una sesión abierta.
+    pub(crate) fn open(&self) {
+        *self.sessions.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+    }
+
+    /// Registra el cierre de una sesión. Si era la última, vacía la tabla. El lock de las
+    /// sesiones se mantiene mientras se vacía, para que ninguna sesión nueva vea la tabla a
+    /// medio vaciar.
+    pub(crate) fn close(&self) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        *sessions -= 1;
+        if *sessions == 0 {
+            *self.table.write().unwrap_or_else(|e| e.into_inner()) = Interner::default();
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Busca o asigna el identificador de un nombre.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Nombre a internar.
+    pub(crate) fn intern(&self, name: &str) -> (Arc<str>, Id) {
+        debug_assert!(
+            *self.sessions.lock().unwrap_or_else(|e| e.into_inner()) > 0,
+            "se internó un nombre fuera de una sesión"
+        );
+        if let Some((name, id)) = self
+            .table
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .ids
+            .get_key_value(name)
+        {
+            return (Arc::clone(name), *id);
+        }
+        let mut table = self.table.write().unwrap_or_else(|e| e.into_inner());
+        if let Some((name, id)) = table.ids.get_key_value(name) {
+            return (Arc::clone(name), *id);
+        }
+        let id = table.names.len() as Id;
+        let name: Arc<str> = Arc::from(name);
+        table.names.push(Arc::clone(&name));
+        table.ids.insert(Arc::clone(&name), id);
+        (name, id)
+    }
+
+    /// Devuelve la cantidad de nombres internados.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.table
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .names
+            .len()
+    }
+
+    /// Devuelve la generación actual de la tabla.
+    #[cfg(test)]
+    pub(crate) fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+}
+
+/// Tabla global.
+static INTERNER: LazyLock<SharedInterner> = LazyLock::new(SharedInterner::default);
+
+thread_local! {
+    /// Copia local de los identificadores ya consultados por el hilo, para no tomar el lock
+    /// de la tabla global en cada línea, junto con la generación de la tabla de la que se
+    /// tomaron.
+    static LOCAL_IDS: RefCell<(u64, HashMap<Arc<str>, Id>)> = RefCell::new((0, HashMap::new()));
+}
+
+/// Session: Mantiene válidos los identificadores asignados mientras exista. Todo el código
+/// que interna nombres o guarda identificadores tiene que hacerlo dentro de una sesión.
+pub(crate) struct Session(());
+
+impl Session {
+    /// Abre una sesión.
+    pub(crate) fn start() -> Session {
+        INTERNER.open();
+        Session(())
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        INTERNER.close();
+    }
+}
+
+/// Devuelve el identificador de un nombre, asignándole uno nuevo si no lo tenía.
+///
+/// # Arguments
+///
+/// * `name` - Nombre a internar.
+pub(crate) fn intern(name: &str) -> Id {
+    let generation = INTERNER.generation.load(Ordering::SeqCst);
+    LOCAL_IDS.with(|local_ids| {
+        let mut local_ids = local_ids.borrow_mut();
+        if local_ids.0 != generation {
+            *local_ids = (generation, HashMap::new());
+        }
+        if let Some(id) = local_ids.1.get(name) {
+            return *id;
+        }
+        let (name, id) = INTERNER.intern(name);
+        local_ids.1.insert(name, id);
+        id
+    })
+}
+
+/// Names: Acceso de lectura a la tabla, para resolver varios identificadores tomando el lock
+/// una sola vez.
+pub(crate) struct Names(RwLockReadGuard<'static, Interner>);
+
+impl Names {
+    /// Devuelve el nombre de un identificador.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Identificador a resolver.
+    pub(crate) fn get(&self, id: Id) -> &str {
+        &self.0.names[id as usize]
+    }
+}
+
+/// Toma el lock de lectura de la tabla para resolver identificadores.
+pub(crate) fn names() -> Names {
+    Names(INTERNER.table.read().unwrap_or_else(|e| e.into_inner()))
+}
@@ -1,48 +1,140 @@
+mod backends;
+mod bench;
+mod budget;
+mod cache;
+mod cancel;
+mod checkpoint;
+mod config;
+mod distributed;
+mod intern;
+mod output;
+mod plan;
+mod pools;
+mod processes;
 mod processors;
+mod progress;
+mod report;
 mod structs;
 mod test;
+mod watch;
 
-use processors::{list_files, process_files, process_totals};
-use rayon::ThreadPoolBuilder;
-use serde_json::to_string_pretty;
+use cancel::{install_handler, partial_exit_code};
+use config::{Command, Config, USAGE};
+use output::write_result;
+use pools::Pools;
+use processes::process_in_children;
+use processors::{input_files, process_files, process_totals};
+use report::{as_ms, peak_rss_mb, PerfReport};
 use std::env;
 use std::time::Instant;
 
-/// Setea el número de workers
-fn configure_workers() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("Seteo de workers erroneo, debe ingresar como argumento la cantidad deseada\n");
-        std::process::exit(1);
+/// Parsea los argumentos del programa.
+///
+/// # Returns
+///
+/// La `Config` del programa. Si los argumentos son erróneos, termina el proceso.
+fn parse_config() -> Config {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match Config::from_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}\n{}\n", e, USAGE);
+            std::process::exit(1);
+        }
     }
+}
 
-    let num_threads: usize = args[1].parse().expect("Se requiere un número entero");
-    match ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .build_global()
-    {
-        Ok(_) => {}
-        Err(e) => eprintln!("Error al crear ThreadPool: {}", e),
+/// Crea los ThreadPools de la corrida. Si no se pueden crear, termina el proceso.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del procesamiento.
+fn build_pools(config: &Config) -> Pools {
+    match Pools::from_config(config) {
+        Ok(pools) => pools,
+        Err(e) => {
+            eprintln!("Error al crear ThreadPool: {}", e);
+            std::process::exit(1);
+        }
     }
 }
 
 fn main() {
-    // se setea la cantidad de workers
-    configure_workers();
+    let config = parse_config();
 
+    // ante SIGINT/SIGTERM se corta el procesamiento y se escribe un resultado parcial
+    install_handler();
+
+    match config.command {
+        Command::Run => run(&config, &build_pools(&config)),
+        Command::Watch => watch::watch(&config, &build_pools(&config)),
+        Command::Bench => bench::bench(&config),
+        Command::Coordinator => distributed::coordinator(&config, &build_pools(&config)),
+        Command::Worker => distributed::worker(&config, &build_pools(&config)),
+        Command::Plan => plan::plan(&config),
+        Command::Merge => plan::merge(&config, &build_pools(&config)),
+        Command::Child => processes::child(&config, &build_pools(&config)),
+    }
+}
+
+/// Procesa los archivos de entrada una vez y escribe el resultado.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida.
+fn run(config: &Config, pools: &Pools) {
     let start = Instant::now();
+    let mut report = config.report.as_ref().map(|_| PerfReport {
+        workers: config.workers,
+        ..PerfReport::default()
+    });
 
-    let files = list_files("/data");
+    // con --shard los archivos se toman del manifest
+    let files = match config.shard {
+        Some(_) => Vec::new(),
+        None => input_files(config),
+    };
+    let listing = start.elapsed();
 
     // se obtiene una estructura con la forma del json final
-    let mut result_data = process_files(&files);
+    let mut result_data = match (config.shard, config.processes) {
+        (Some(_), _) => plan::process_shard(config, pools),
+        (None, Some(processes)) => process_in_children(&files, processes, pools),
+        (None, None) => process_files(&files, config, pools, report.as_mut()),
+    };
 
     // se calculan los totals sobre lo procesado
-    process_totals(&mut result_data);
+    let totals_start = Instant::now();
+    pools.parse().install(|| process_totals(&mut result_data));
+    let totals = totals_start.elapsed();
 
-    // Imprime la cadena JSON resultante
-    let json_string =
-        to_string_pretty(&result_data).expect("Error al serializar el HashMap a JSON");
-    println!("{}", json_string);
+    // Escribe la cadena JSON resultante
+    let serialization_start = Instant::now();
+    if let Err(e) = write_result(&result_data, config.output.as_deref()) {
+        eprintln!("Error al escribir el resultado: {}", e);
+        std::process::exit(1);
+    }
+    let serialization = serialization_start.elapsed();
     eprintln!("Tiempo transcurrido: {:?}", start.elapsed());
+    let peak_rss_mb = peak_rss_mb();
+    if let Some(peak_rss_mb) = peak_rss_mb {
+        eprintln!("Memoria máxima: {:.1} MB", peak_rss_mb);
+    }
+
+    if let (Some(mut report), Some(path)) = (report, &config.report) {
+        report.phases.listing_ms = as_ms(listing);
+        report.phases.totals_ms = as_ms(totals);
+        report.phases.serialization_ms = as_ms(serialization);
+        report.phases.total_ms = as_ms(start.elapsed());
+        report.peak_rss_mb = peak_rss_mb;
+        if let Err(e) = report.write(path) {
+            eprintln!("Error al escribir el reporte {}: {}", path.display(), e);
+        }
+    }
+
+    if result_data.incomplete.is_some() {
+        eprintln!("Procesamiento interrumpido: el resultado es parcial");
+        std::process::exit(partial_exit_code());
+    }
 }
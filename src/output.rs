@@ -0,0 +1,46 @@
+//! Este módulo escribe el resultado final en stdout o en un archivo.
+
+use crate::structs::ResultData;
+use serde_json::to_string_pretty;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Escribe un archivo de forma atómica: primero en un temporal y luego lo renombra.
+///
+/// # Arguments
+///
+/// * `path` - Ruta final del archivo.
+/// * `contents` - Contenido a escribir.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede escribir o renombrar el archivo.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_name);
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+/// Serializa el resultado a JSON y lo escribe en el archivo de salida o en stdout.
+///
+/// # Arguments
+///
+/// * `result_data` - Resultado a escribir.
+/// * `output` - Archivo de salida. Si es `None`, se imprime por stdout.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede escribir el archivo de salida.
+pub(crate) fn write_result(result_data: &ResultData, output: Option<&Path>) -> io::Result<()> {
+    let json_string = to_string_pretty(result_data).expect("Error al serializar el HashMap a JSON");
+    match output {
+        Some(path) => write_atomic(path, json_string.as_bytes()),
+        None => {
+            println!("{}", json_string);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,497 @@
+//! Este módulo implementa el subcomando `plan`, que reparte los archivos de entrada en shards
+//! balanceados por tamaño, el procesamiento de un shard con `--manifest` y `--shard i/N`, y el
+//! subcomando `merge`, que combina los resultados de los shards.
+//!
+//! El manifest es un JSON con las partes de cada shard. Una parte es un archivo completo o, si
+//! el archivo supera el tamaño de chunk, un rango de bytes alineado a saltos de línea. Así un
+//! planificador externo (cron, Slurm, Kubernetes) puede correr cada shard por separado y luego
+//! combinar sus salidas.
+
+use crate::budget::MemoryBudget;
+use crate::cancel::{is_cancelled, partial_exit_code};
+use crate::checkpoint::hash_file;
+use crate::config::Config;
+use crate::intern::{intern, Session};
+use crate::output::{write_atomic, write_result};
+use crate::pools::Pools;
+use crate::processors::{
+    absolute_paths, balance, elapsed_ns, get_site_name, incomplete_data, input_files, is_gzip,
+    merge_results, not_processed, process_file_with_state, process_range, process_totals,
+    processed_result, split_ranges, status_from, LineOptions,
+};
+use crate::structs::{ByteRange, FileResult, IncompleteData, ResultData, ShardId};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Bytes por MB.
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Manifest: Asignación de las partes de los archivos de entrada a cada shard.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct Manifest {
+    /// Tamaño a partir del cual los archivos se dividieron en rangos.
+    pub(crate) chunk_size: u64,
+    pub(crate) shards: Vec<ShardPlan>,
+}
+
+/// ShardPlan: Partes asignadas a un shard.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub(crate) struct ShardPlan {
+    /// Suma de los bytes de las partes.
+    pub(crate) bytes: u64,
+    pub(crate) parts: Vec<Part>,
+}
+
+/// Part: Archivo completo o rango de bytes de un archivo.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct Part {
+    pub(crate) path: PathBuf,
+    /// Tamaño del archivo al generar el manifest, para detectar si cambió.
+    pub(crate) file_size: u64,
+    /// Rango a procesar. Si no se indica, se procesa el archivo completo.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) range: Option<ByteRange>,
+}
+
+impl Part {
+    /// Devuelve la cantidad de bytes de la parte.
+    fn bytes(&self) -> u64 {
+        self.range
+            .map_or(self.file_size, |range| range.end - range.start)
+    }
+}
+
+/// Divide los archivos en partes: los archivos regulares sin comprimir que superan
+/// `chunk_size` se dividen en rangos, el resto queda completo.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos de entrada.
+/// * `chunk_size` - Tamaño a partir del cual un archivo se divide.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer algún archivo.
+fn split_parts(paths: &[PathBuf], chunk_size: u64) -> io::Result<Vec<Part>> {
+    let mut parts = vec![];
+    for path in paths {
+        let metadata = path.metadata()?;
+        let file_size = metadata.len();
+        if metadata.is_file() && !is_gzip(path) && file_size > chunk_size {
+            parts.extend(
+                split_ranges(path, chunk_size)?
+                    .into_iter()
+                    .map(|range| Part {
+                        path: path.clone(),
+                        file_size,
+                        range: Some(range),
+                    }),
+            );
+        } else {
+            parts.push(Part {
+                path: path.clone(),
+                file_size,
+                range: None,
+            });
+        }
+    }
+    Ok(parts)
+}
+
+/// Genera el manifest de los archivos de entrada. Las rutas se guardan absolutas, para que
+/// cada shard se pueda correr desde otro directorio.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos de entrada.
+/// * `shards` - Cantidad de shards.
+/// * `chunk_size` - Tamaño a partir del cual un archivo se divide en rangos.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer algún archivo.
+pub(crate) fn build_manifest(
+    paths: &[PathBuf],
+    shards: usize,
+    chunk_size: u64,
+) -> io::Result<Manifest> {
+    let parts = split_parts(&absolute_paths(paths.to_vec()), chunk_size)?;
+    let sizes: Vec<u64> = parts.iter().map(Part::bytes).collect();
+    let shards = balance(&sizes, shards)
+        .into_iter()
+        .map(|members| ShardPlan {
+            bytes: members.iter().map(|&index| sizes[index]).sum(),
+            parts: members
+                .into_iter()
+                .map(|index| parts[index].clone())
+                .collect(),
+        })
+        .collect();
+    Ok(Manifest { chunk_size, shards })
+}
+
+/// Lee un manifest generado con `plan`.
+///
+/// # Arguments
+///
+/// * `path` - Archivo del manifest.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer o parsear el archivo.
+pub(crate) fn load_manifest(path: &Path) -> io::Result<Manifest> {
+    let contents = fs::read(path)?;
+    serde_json::from_slice(&contents).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Devuelve las partes de un shard, verificando que el manifest corresponda a `count` shards y
+/// que los archivos no hayan cambiado de tamaño desde que se generó.
+///
+/// # Arguments
+///
+/// * `manifest` - Manifest a consumir.
+/// * `index` - Shard a procesar, desde 0.
+/// * `count` - Cantidad de shards indicada en `--shard`.
+///
+/// # Returns
+///
+/// Las partes del shard o un mensaje de error.
+pub(crate) fn shard_parts(
+    manifest: &Manifest,
+    index: usize,
+    count: usize,
+) -> Result<&[Part], String> {
+    if manifest.shards.len() != count {
+        return Err(format!(
+            "El manifest tiene {} shards y se indicó --shard {}/{}",
+            manifest.shards.len(),
+            index,
+            count
+        ));
+    }
+    let parts = &manifest.shards[index].parts;
+    for part in parts {
+        let size = part.path.metadata().map(|metadata| metadata.len());
+        if size.as_ref().ok() != Some(&part.file_size) {
+            return Err(format!(
+                "El archivo {} cambió desde que se generó el manifest",
+                part.path.display()
+            ));
+        }
+    }
+    Ok(parts)
+}
+
+/// Procesa una parte del manifest.
+///
+/// # Arguments
+///
+/// * `part` - Parte a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida.
+/// * `budget` - Presupuesto de memoria, si está habilitado.
+fn process_part(
+    part: &Part,
+    config: &Config,
+    pools: &Pools,
+    budget: Option<&MemoryBudget>,
+) -> FileResult {
+    let Some(range) = part.range else {
+        return process_file_with_state(&part.path, config, pools, None, budget);
+    };
+    if is_cancelled() {
+        return not_processed(&part.path);
+    }
+    let start = Instant::now();
+    let site = intern(&get_site_name(&part.path));
+    match process_range(&part.path, range, site, LineOptions::from(config), None) {
+        Ok((accumulator, stats, interrupted)) => {
+            let mut result =
+                processed_result(&part.path, accumulator, stats, status_from(interrupted));
+            result.stats.elapsed_ns = elapsed_ns(start);
+            result
+        }
+        Err(e) => {
+            eprintln!("Error al abrir archivo {}: {}", part.path.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Procesa el shard indicado en `--shard` del manifest de `--manifest`. Si el manifest no
+/// corresponde, termina el proceso.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del procesamiento. Debe tener `manifest` y `shard`.
+/// * `pools` - ThreadPools de la corrida.
+///
+/// # Returns
+///
+/// El `ResultData` de las partes del shard, sin totals, con el shard y la lista de sus archivos
+/// y marcado como incompleto si alguna parte no se procesó por completo.
+pub(crate) fn process_shard(config: &Config, pools: &Pools) -> ResultData {
+    let path = config
+        .manifest
+        .as_deref()
+        .expect("--shard requiere --manifest");
+    let (index, count) = config.shard.expect("--manifest requiere --shard");
+    let manifest = load_manifest(path).unwrap_or_else(|e| {
+        eprintln!("Error al leer el manifest {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+    let parts = shard_parts(&manifest, index, count).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let manifest_hash = hash_file(path).unwrap_or_else(|e| {
+        eprintln!("Error al leer el manifest {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+
+    let _session = Session::start();
+    let budget = config.memory_budget.map(MemoryBudget::new);
+    let results: Vec<FileResult> = pools.io().install(|| {
+        parts
+            .par_iter()
+            .map(|part| process_part(part, config, pools, budget.as_ref()))
+            .collect()
+    });
+    let incomplete = incomplete_data(&results);
+    let mut files: Vec<String> = results
+        .iter()
+        .map(|result| result.path.display().to_string())
+        .collect();
+    files.sort();
+    files.dedup();
+    let mut result_data = pools.parse().install(|| merge_results(results));
+    result_data.incomplete = incomplete;
+    result_data.files = files;
+    result_data.shard = Some(ShardId {
+        manifest: manifest_hash,
+        index,
+        count,
+    });
+    result_data
+}
+
+/// Ejecuta el subcomando `plan`: genera el manifest de los archivos de entrada y lo escribe en
+/// `--output` o por stdout.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del programa. `workers` es la cantidad de shards.
+pub fn plan(config: &Config) {
+    let paths = input_files(config);
+    let manifest =
+        build_manifest(&paths, config.workers, config.chunk_size()).unwrap_or_else(|e| {
+            eprintln!("Error al leer los archivos de entrada: {}", e);
+            std::process::exit(1);
+        });
+    for (index, shard) in manifest.shards.iter().enumerate() {
+        eprintln!(
+            "Shard {}: {} partes, {:.1} MB",
+            index,
+            shard.parts.len(),
+            shard.bytes as f64 / MB
+        );
+    }
+
+    let json_string =
+        serde_json::to_string_pretty(&manifest).expect("Error al serializar el manifest a JSON");
+    match &config.output {
+        Some(path) => {
+            if let Err(e) = write_atomic(path, json_string.as_bytes()) {
+                eprintln!("Error al escribir el manifest: {}", e);
+                std::process::exit(1);
+            }
+        }
+        None => println!("{}", json_string),
+    }
+}
+
+/// Devuelve el detalle de archivos del resultado de un shard. Si el shard es completo, todos
+/// sus archivos están completos.
+///
+/// # Arguments
+///
+/// * `result_data` - Resultado del shard. Se le quitan el detalle y la lista de archivos.
+fn shard_incomplete(result_data: &mut ResultData) -> IncompleteData {
+    let files = std::mem::take(&mut result_data.files);
+    result_data
+        .incomplete
+        .take()
+        .unwrap_or_else(|| IncompleteData {
+            complete_files: files,
+            partial_files: vec![],
+            not_processed_files: vec![],
+        })
+}
+
+/// Combina el detalle de archivos de dos shards.
+///
+/// # Arguments
+///
+/// * `a` - Detalle del primer shard.
+/// * `b` - Detalle del segundo shard.
+fn combine_incomplete(mut a: IncompleteData, b: IncompleteData) -> IncompleteData {
+    a.complete_files.extend(b.complete_files);
+    a.partial_files.extend(b.partial_files);
+    a.not_processed_files.extend(b.not_processed_files);
+    a
+}
+
+/// Termina el detalle de archivos combinado. Un archivo dividido en rangos de varios shards
+/// aparece una sola vez: completo si lo están todos sus rangos, no procesado si no se procesó
+/// ninguno, y parcial en otro caso.
+///
+/// # Arguments
+///
+/// * `incomplete` - Detalle combinado de todos los shards.
+///
+/// # Returns
+///
+/// `None` si todos los archivos están completos, o el detalle en caso contrario.
+fn finish_incomplete(incomplete: IncompleteData) -> Option<IncompleteData> {
+    let complete: BTreeSet<String> = incomplete.complete_files.into_iter().collect();
+    let mut partial: BTreeSet<String> = incomplete.partial_files.into_iter().collect();
+    let mut not_processed: BTreeSet<String> = incomplete.not_processed_files.into_iter().collect();
+    partial.extend(
+        not_processed
+            .intersection(&complete)
+            .cloned()
+            .collect::<Vec<_>>(),
+    );
+    not_processed.retain(|file| !partial.contains(file));
+    if partial.is_empty() && not_processed.is_empty() {
+        return None;
+    }
+    Some(IncompleteData {
+        complete_files: complete
+            .into_iter()
+            .filter(|file| !partial.contains(file) && !not_processed.contains(file))
+            .collect(),
+        partial_files: partial.into_iter().collect(),
+        not_processed_files: not_processed.into_iter().collect(),
+    })
+}
+
+/// Verifica que los resultados a combinar sean de shards distintos de un mismo plan.
+///
+/// # Arguments
+///
+/// * `results` - Ruta y resultado de cada shard.
+///
+/// # Errors
+///
+/// Devuelve un error si algún resultado no es de un shard, es de otro plan o repite un shard.
+fn check_shards(results: &[(&PathBuf, ResultData)]) -> io::Result<()> {
+    let invalid = |path: &PathBuf, message: String| {
+        io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{}: {}", path.display(), message),
+        )
+    };
+    let mut seen: Vec<(&PathBuf, &ShardId)> = vec![];
+    for (path, result_data) in results {
+        let shard = result_data
+            .shard
+            .as_ref()
+            .ok_or_else(|| invalid(path, "no es el resultado de un shard".to_string()))?;
+        if let Some((first_path, first)) = seen.first() {
+            if shard.manifest != first.manifest || shard.count != first.count {
+                return Err(invalid(
+                    path,
+                    format!("es de otro plan que {}", first_path.display()),
+                ));
+            }
+        }
+        if let Some((other, _)) = seen.iter().find(|(_, other)| other.index == shard.index) {
+            return Err(invalid(
+                path,
+                format!(
+                    "repite el shard {}/{} de {}",
+                    shard.index,
+                    shard.count,
+                    other.display()
+                ),
+            ));
+        }
+        seen.push((path, shard));
+    }
+    Ok(())
+}
+
+/// Combina los resultados de varios shards y recalcula los totals.
+///
+/// # Arguments
+///
+/// * `parts` - Resultados de cada shard, tal como los escribe una corrida con `--shard`.
+///
+/// # Returns
+///
+/// El resultado combinado. Si algún shard es parcial, el resultado también.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer o parsear algún resultado, o si no son shards distintos
+/// de un mismo plan.
+pub(crate) fn merge_parts(parts: &[PathBuf]) -> io::Result<ResultData> {
+    let results = parts
+        .par_iter()
+        .map(|path| {
+            let contents = fs::read(path)?;
+            let result_data = serde_json::from_slice::<ResultData>(&contents).map_err(|e| {
+                io::Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+            })?;
+            Ok((path, result_data))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    check_shards(&results)?;
+    let (mut result_data, incomplete) = results
+        .into_par_iter()
+        .map(|(_, mut result_data)| {
+            result_data.shard = None;
+            let incomplete = shard_incomplete(&mut result_data);
+            (result_data, incomplete)
+        })
+        .reduce_with(|(a, a_incomplete), (b, b_incomplete)| {
+            (a + b, combine_incomplete(a_incomplete, b_incomplete))
+        })
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "No hay resultados"))?;
+    result_data.incomplete = finish_incomplete(incomplete);
+    process_totals(&mut result_data);
+    Ok(result_data)
+}
+
+/// Ejecuta el subcomando `merge`: combina los resultados de los shards indicados con `--part`
+/// y escribe el resultado final.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del programa. Debe tener al menos un `part`.
+/// * `pools` - ThreadPools con los que se combinan los resultados.
+pub fn merge(config: &Config, pools: &Pools) {
+    let start = Instant::now();
+    let result_data = pools
+        .parse()
+        .install(|| merge_parts(&config.parts))
+        .unwrap_or_else(|e| {
+            eprintln!("Error al leer los resultados: {}", e);
+            std::process::exit(1);
+        });
+    if let Err(e) = write_result(&result_data, config.output.as_deref()) {
+        eprintln!("Error al escribir el resultado: {}", e);
+        std::process::exit(1);
+    }
+    eprintln!("Tiempo transcurrido: {:?}", start.elapsed());
+    if result_data.incomplete.is_some() {
+        eprintln!("Alguno de los shards es parcial: el resultado es parcial");
+        std::process::exit(partial_exit_code());
+    }
+}
@@ -0,0 +1,66 @@
+//! Este módulo crea los ThreadPools de cada corrida.
+//!
+//! En lugar de configurar el pool global de rayon, que solo puede configurarse una vez por
+//! proceso, cada corrida crea sus pools y los pasa al procesamiento. Así se pueden ejecutar
+//! varias configuraciones en el mismo proceso.
+
+use crate::config::Config;
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder};
+
+/// Pools: ThreadPools de una corrida.
+pub struct Pools {
+    /// Recorre los archivos: los abre y lee con buffer sus líneas en lotes que entrega al pool
+    /// de parseo. Los archivos mapeados en memoria o divididos en rangos se leen en el pool de
+    /// parseo.
+    io: ThreadPool,
+    /// Parsea y agrega las líneas leídas.
+    parse: ThreadPool,
+}
+
+impl Pools {
+    /// Crea los pools de una corrida.
+    ///
+    /// # Arguments
+    ///
+    /// * `io_workers` - Cantidad de workers del pool de lectura.
+    /// * `parse_workers` - Cantidad de workers del pool de parseo.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve un error si no puede crear alguno de los pools.
+    pub fn new(io_workers: usize, parse_workers: usize) -> Result<Pools, ThreadPoolBuildError> {
+        Ok(Pools {
+            io: ThreadPoolBuilder::new()
+                .num_threads(io_workers)
+                .thread_name(|index| format!("io-{}", index))
+                .build()?,
+            parse: ThreadPoolBuilder::new()
+                .num_threads(parse_workers)
+                .thread_name(|index| format!("parse-{}", index))
+                .build()?,
+        })
+    }
+
+    /// Crea los pools con las cantidades de workers de la configuración.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuración del procesamiento.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve un error si no puede crear alguno de los pools.
+    pub fn from_config(config: &Config) -> Result<Pools, ThreadPoolBuildError> {
+        Pools::new(config.io_workers(), config.workers)
+    }
+
+    /// Devuelve el pool de lectura.
+    pub fn io(&self) -> &ThreadPool {
+        &self.io
+    }
+
+    /// Devuelve el pool de parseo.
+    pub fn parse(&self) -> &ThreadPool {
+        &self.parse
+    }
+}
@@ -0,0 +1,276 @@
+//! Este módulo implementa `--processes`: reparte los archivos entre procesos hijos del mismo
+//! ejecutable y combina sus resultados.
+//!
+//! Cada hijo se lanza con el subcomando interno `child`, recibe por stdin la lista de archivos
+//! de su shard, balanceado por tamaño, y escribe por stdout el resultado parcial de cada
+//! archivo, con el mismo formato de mensajes que el modo distribuido. Si un hijo termina con
+//! error, los archivos de los que no llegó a enviar resultado se procesan en un hijo nuevo.
+
+use crate::budget::MemoryBudget;
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::distributed::{read_frame, write_frame, RemoteResult};
+use crate::intern::Session;
+use crate::pools::Pools;
+use crate::processors::{
+    balance, incomplete_data, merge_results, not_processed, try_process_file_with_state,
+};
+use crate::structs::{FileResult, ResultData};
+use rayon::prelude::*;
+use std::env;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+
+/// Cantidad de veces que se reintentan los archivos de un hijo que terminó con error.
+const CHILD_RETRIES: usize = 1;
+
+/// Devuelve los argumentos con los que se lanza cada hijo: los del proceso actual, sin
+/// `--processes` ni las opciones de salida, precedidos por el subcomando `child`.
+fn child_args() -> Vec<String> {
+    let mut args = vec!["child".to_string()];
+    let mut current = env::args().skip(1);
+    while let Some(arg) = current.next() {
+        match arg.as_str() {
+            "--processes" | "--output" => {
+                current.next();
+            }
+            _ => args.push(arg),
+        }
+    }
+    args
+}
+
+/// Lanza un hijo y le envía por stdin los archivos de su shard.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos del shard.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede lanzar el hijo o escribir su entrada.
+fn spawn_child(paths: &[PathBuf]) -> io::Result<Child> {
+    let mut child = Command::new(env::current_exe()?)
+        .args(child_args())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        if let Err(e) = write_frame(&mut stdin, &paths) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+    }
+    Ok(child)
+}
+
+/// Lee los resultados que envía un hijo hasta que cierra su salida.
+///
+/// # Arguments
+///
+/// * `stdout` - Salida del hijo.
+/// * `shard` - Posiciones de los archivos del hijo en la lista completa.
+/// * `paths` - Archivos de entrada.
+/// * `results` - Resultados recibidos, por posición en la lista completa.
+///
+/// # Errors
+///
+/// Devuelve un error si la salida del hijo se corta o no tiene el formato esperado.
+fn receive_results(
+    stdout: ChildStdout,
+    shard: &[usize],
+    paths: &[PathBuf],
+    results: &Mutex<Vec<Option<FileResult>>>,
+) -> io::Result<()> {
+    let mut stdout = BufReader::new(stdout);
+    loop {
+        let (position, result): (usize, RemoteResult) = match read_frame(&mut stdout) {
+            Ok(message) => message,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let index = *shard.get(position).ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                "Resultado de un archivo desconocido",
+            )
+        })?;
+        let result = result.into_file_result(&paths[index]);
+        results.lock().unwrap_or_else(|e| e.into_inner())[index] = Some(result);
+    }
+}
+
+/// Lanza un hijo por shard y espera sus resultados.
+///
+/// # Arguments
+///
+/// * `shards` - Posiciones de los archivos de cada shard.
+/// * `paths` - Archivos de entrada.
+/// * `results` - Resultados recibidos, por posición en la lista completa.
+///
+/// # Returns
+///
+/// La cantidad de hijos que terminaron con error.
+fn run_children(
+    shards: &[Vec<usize>],
+    paths: &[PathBuf],
+    results: &Mutex<Vec<Option<FileResult>>>,
+) -> usize {
+    thread::scope(|scope| {
+        let handles: Vec<_> = shards
+            .iter()
+            .filter(|shard| !shard.is_empty())
+            .map(|shard| {
+                scope.spawn(move || {
+                    let files: Vec<PathBuf> = shard.iter().map(|&i| paths[i].clone()).collect();
+                    let mut child = spawn_child(&files)?;
+                    let received = match child.stdout.take() {
+                        Some(stdout) => receive_results(stdout, shard, paths, results),
+                        None => Ok(()),
+                    };
+                    let status = child.wait()?;
+                    received?;
+                    if status.success() {
+                        Ok(())
+                    } else {
+                        Err(io::Error::other(format!("terminó con {}", status)))
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::other("panic")))
+            })
+            .filter(|outcome| {
+                if let Err(e) = outcome {
+                    eprintln!("Error en un proceso hijo: {}", e);
+                }
+                outcome.is_err()
+            })
+            .count()
+    })
+}
+
+/// Procesa los archivos en `processes` procesos hijos y combina sus resultados.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `processes` - Cantidad de procesos hijos.
+/// * `pools` - ThreadPools del proceso padre, donde se combinan los resultados.
+///
+/// # Returns
+///
+/// El `ResultData` combinado, marcado como incompleto si algún archivo no se pudo procesar.
+pub(crate) fn process_in_children(
+    paths: &[PathBuf],
+    processes: usize,
+    pools: &Pools,
+) -> ResultData {
+    let _session = Session::start();
+    let results = Mutex::new((0..paths.len()).map(|_| None).collect::<Vec<_>>());
+    let sizes: Vec<u64> = paths
+        .iter()
+        .map(|path| path.metadata().map_or(0, |metadata| metadata.len()))
+        .collect();
+    let mut shards = balance(&sizes, processes);
+    for attempt in 0..=CHILD_RETRIES {
+        let failed = run_children(&shards, paths, &results);
+        if failed == 0 || attempt == CHILD_RETRIES || is_cancelled() {
+            break;
+        }
+        let missing: Vec<usize> = {
+            let received = results.lock().unwrap_or_else(|e| e.into_inner());
+            (0..paths.len())
+                .filter(|&index| received[index].is_none())
+                .collect()
+        };
+        if missing.is_empty() {
+            break;
+        }
+        eprintln!(
+            "Se reintentan {} archivos sin resultado en un proceso nuevo",
+            missing.len()
+        );
+        shards = vec![missing];
+    }
+
+    let results: Vec<FileResult> = results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .zip(paths)
+        .map(|(result, path)| result.unwrap_or_else(|| not_processed(path)))
+        .collect();
+    let incomplete = incomplete_data(&results);
+    let mut result_data = pools.parse().install(|| merge_results(results));
+    result_data.incomplete = incomplete;
+    result_data
+}
+
+/// Procesa los archivos recibidos en `input` y escribe en `output` el resultado de cada uno
+/// a medida que termina. Es el trabajo de cada proceso hijo. Un archivo que no se puede abrir
+/// o leer se informa como no procesado, sin terminar el hijo.
+///
+/// # Arguments
+///
+/// * `input` - Entrada con la lista de archivos.
+/// * `output` - Salida donde se escriben los resultados.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools del hijo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer la lista de archivos o escribir un resultado.
+pub(crate) fn serve_child(
+    mut input: impl Read,
+    output: impl Write + Send,
+    config: &Config,
+    pools: &Pools,
+) -> io::Result<()> {
+    let paths: Vec<PathBuf> = read_frame(&mut input)?;
+    let _session = Session::start();
+    let budget = config.memory_budget.map(MemoryBudget::new);
+    let output = Mutex::new(output);
+    pools.io().install(|| {
+        paths
+            .par_iter()
+            .enumerate()
+            .try_for_each(|(position, path)| {
+                let result =
+                    try_process_file_with_state(path, config, pools, None, budget.as_ref())
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error al abrir archivo {}: {}", path.display(), e);
+                            not_processed(path)
+                        });
+                let message = (position, RemoteResult::from(result));
+                write_frame(
+                    &mut *output.lock().unwrap_or_else(|e| e.into_inner()),
+                    &message,
+                )
+            })
+    })
+}
+
+/// Ejecuta el subcomando interno `child`: procesa los archivos recibidos por stdin y escribe
+/// sus resultados por stdout.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools del hijo.
+pub fn child(config: &Config, pools: &Pools) {
+    let stdout = BufWriter::new(io::stdout());
+    if let Err(e) = serve_child(io::stdin().lock(), stdout, config, pools) {
+        eprintln!("Error en el proceso hijo: {}", e);
+        std::process::exit(1);
+    }
+}
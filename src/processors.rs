@@ -1,46 +1,107 @@
 //! Este módulo contiene funciones y tipos de datos para el procesamiento de archivos JSON.
 
+use crate::backends::{actors, async_io, pipeline, sharded, threads};
+use crate::budget::MemoryBudget;
+use crate::cache;
+use crate::cancel::is_cancelled;
+use crate::checkpoint;
+use crate::config::{Backend, Config, Parser, Schedule};
+use crate::intern::{intern, Id, Session};
+use crate::pools::Pools;
+use crate::progress::{Progress, ProgressReporter};
+use crate::report::{as_ms, FileReport, PerfReport, ShardReport};
 use crate::structs::{
-    JsonStructure, LineJsonStructure, ResultData, SiteData, TagData, CHATTY_TAGS_MAX,
+    Accumulator, ByteRange, FileResult, FileSource, FileStats, FileStatus, IncompleteData,
+    LineJsonStructure, ResultData, SiteData, Tag, TagData, CHATTY_TAGS_MAX,
 };
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
 use rayon::prelude::*;
-use serde_json::from_str;
+use rayon::ThreadPool;
+use serde_json::from_slice;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 use std::fs::{read_dir, File};
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Cantidad de preguntas por línea.
-const QUESTIONS_PER_LINE: u32 = 1;
+pub(crate) const QUESTIONS_PER_LINE: u32 = 1;
 
 /// Máximo número de sitios "chatty".
 const CHATTY_SITES_MAX: usize = 10;
 
+/// Extensión de los archivos comprimidos con gzip.
+const GZIP_EXTENSION: &str = ".gz";
+
+/// Tamaño aproximado de los bloques en los que se divide un archivo mapeado en memoria.
+const MMAP_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
 /// Número de padrón.
-const PADRON: u32 = 107587;
+pub const PADRON: u32 = 107587;
+
+/// Directorio de entrada por defecto, relativo al directorio del proyecto.
+const DEFAULT_INPUT_DIR: &str = "/data";
 
-/// Cuenta la cantidad de palabras en un vector de cadenas de texto.
+/// LineOptions: Cómo se procesa cada línea de un archivo.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LineOptions {
+    /// Implementación con la que se parsea cada línea.
+    pub(crate) parser: Parser,
+    /// Indica si se miden los tiempos de parseo y agregación de cada línea. Solo se miden
+    /// cuando se pide el reporte de performance, porque medir cada línea tiene un costo.
+    pub(crate) timed: bool,
+}
+
+impl From<&Config> for LineOptions {
+    fn from(config: &Config) -> Self {
+        LineOptions {
+            parser: config.parser,
+            timed: config.report.is_some(),
+        }
+    }
+}
+
+/// Resultado de un archivo junto con su posición en la lista de entrada y los momentos en que
+/// se empezó y terminó, desde el inicio del procesamiento.
+pub(crate) type TimedResult = (usize, Duration, Duration, FileResult);
+
+/// Lista los archivos en un directorio.
 ///
 /// # Arguments
 ///
-/// * `texts` - Vector de cadenas de texto.
+/// * `directory` - Ruta del directorio, relativa al directorio del proyecto.
 ///
 /// # Returns
 ///
-/// La cantidad total de palabras en el vector de cadenas de texto.
-pub fn word_counter(texts: &[String]) -> u32 {
-    texts
-        .iter()
-        .map(|text| text.split_whitespace().count() as u32)
-        .sum()
+/// Un vector de rutas de archivos dentro del directorio especificado.
+///
+/// # Errors
+///
+/// Puede devolver un error si no puede leer el directorio especificado.
+#[cfg(test)]
+pub fn list_files(directory: &str) -> Vec<PathBuf> {
+    list_dir(&project_dir(directory))
+}
+
+/// Devuelve la ruta de un directorio relativo al directorio del proyecto.
+///
+/// # Arguments
+///
+/// * `directory` - Ruta del directorio, relativa al directorio del proyecto.
+fn project_dir(directory: &str) -> PathBuf {
+    PathBuf::from(format!("{}/{}", env!("CARGO_MANIFEST_DIR"), directory))
 }
 
 /// Lista los archivos en un directorio.
 ///
 /// # Arguments
 ///
-/// * `directory` - Ruta del directorio.
+/// * `dir_path` - Ruta del directorio.
 ///
 /// # Returns
 ///
@@ -49,43 +110,113 @@ pub fn word_counter(texts: &[String]) -> u32 {
 /// # Errors
 ///
 /// Puede devolver un error si no puede leer el directorio especificado.
-pub fn list_files(directory: &str) -> Vec<PathBuf> {
-    let dir_path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), directory);
-    match read_dir(&dir_path) {
+pub fn list_dir(dir_path: &Path) -> Vec<PathBuf> {
+    match read_dir(dir_path) {
         Ok(entries) => entries.flatten().map(|entry| entry.path()).collect(),
         Err(e) => {
-            eprintln!("Error al leer el directorio {}: {}", dir_path, e);
+            eprintln!("Error al leer el directorio {}: {}", dir_path.display(), e);
             process::exit(1);
         }
     }
 }
 
-/// Toma una estructura `LineJsonStructure` y genera un sub `ResultData` con su información.
+/// Lista los archivos de los directorios de entrada de la configuración.
 ///
 /// # Arguments
 ///
-/// * `line_struct` - Estructura `LineJsonStructure` que contiene la información de una línea.
-/// * `json_struct` - Estructura `JsonStructure` que contiene la información del archivo JSON.
+/// * `config` - Configuración del procesamiento.
 ///
 /// # Returns
 ///
-/// Un `ResultData` generado a partir de la línea y la estructura JSON dadas.
-pub fn generate_result_data_from_line(
-    line_struct: LineJsonStructure,
-    mut json_struct: JsonStructure,
-) -> ResultData {
-    json_struct.load_info(line_struct);
+/// Un vector con las rutas de todos los archivos de entrada.
+pub fn input_files(config: &Config) -> Vec<PathBuf> {
+    input_dirs(config)
+        .iter()
+        .flat_map(|dir| list_dir(dir))
+        .collect()
+}
+
+/// Convierte las rutas en absolutas, para que las pueda abrir otro proceso que corre en otro
+/// directorio. No se resuelven los enlaces simbólicos, porque el nombre del sitio sale del
+/// nombre del archivo. Una ruta que no se puede convertir se deja como está.
+///
+/// # Arguments
+///
+/// * `paths` - Rutas a convertir.
+pub(crate) fn absolute_paths(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths
+        .into_iter()
+        .map(|path| std::path::absolute(&path).unwrap_or(path))
+        .collect()
+}
 
-    let words_count = word_counter(&json_struct.texts);
-    let mut tags: HashMap<String, TagData> = HashMap::new();
-    for tag in &json_struct.tags {
-        let tag_data = TagData::new(QUESTIONS_PER_LINE, words_count);
-        tags.insert(tag.clone(), tag_data);
+/// Devuelve los directorios de entrada de la configuración. Si no se indica ninguno, se usa
+/// el directorio `data` del proyecto.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del procesamiento.
+pub fn input_dirs(config: &Config) -> Vec<PathBuf> {
+    if config.inputs.is_empty() {
+        return vec![project_dir(DEFAULT_INPUT_DIR)];
     }
-    let site_data = SiteData::new(QUESTIONS_PER_LINE, words_count, tags);
-    let mut site_subhash: HashMap<String, SiteData> = HashMap::new();
-    site_subhash.insert(json_struct.site.clone(), site_data.clone());
-    ResultData::new(PADRON, site_subhash, site_data.tags)
+    config.inputs.clone()
+}
+
+/// Agrega la información de una línea al `Accumulator` de un worker.
+///
+/// # Arguments
+///
+/// * `accumulator` - `Accumulator` del worker.
+/// * `line_struct` - Estructura `LineJsonStructure` que contiene la información de una línea.
+/// * `site` - Identificador del sitio del archivo.
+fn add_line(accumulator: &mut Accumulator, line_struct: LineJsonStructure, site: Id) {
+    let words_count = line_struct.words.0;
+    let counters = accumulator.sites.entry(site).or_default();
+    counters.questions += QUESTIONS_PER_LINE;
+    counters.words += words_count;
+
+    for tag in line_tags(&line_struct) {
+        let tag_data = counters.tag_mut(tag);
+        tag_data.questions += QUESTIONS_PER_LINE;
+        tag_data.words += words_count;
+    }
+}
+
+/// Interna las etiquetas de una línea. Una etiqueta repetida en la misma línea cuenta una sola
+/// vez.
+///
+/// # Arguments
+///
+/// * `line_struct` - Estructura `LineJsonStructure` que contiene la información de una línea.
+fn line_tags(line_struct: &LineJsonStructure) -> Vec<Id> {
+    let mut tags: Vec<Id> = Vec::with_capacity(line_struct.tags.len());
+    for Tag(tag) in &line_struct.tags {
+        let tag = intern(tag);
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// Parsea una línea y devuelve su cantidad de palabras y sus etiquetas internadas, para
+/// agregarla fuera de un `Accumulator`.
+///
+/// # Arguments
+///
+/// * `line` - Bytes de la línea del archivo, sin el salto de línea.
+/// * `parser` - Implementación con la que se parsea la línea.
+///
+/// # Errors
+///
+/// Devuelve el mensaje de error si la línea no es un JSON válido.
+pub(crate) fn parse_counts(line: &[u8], parser: Parser) -> Result<(u32, Vec<Id>), String> {
+    let mut counts = Err(String::new());
+    with_parsed_line(line, parser, |parsed| {
+        counts = parsed.map(|line_struct| (line_struct.words.0, line_tags(&line_struct)));
+    });
+    counts
 }
 
 /// Obtiene el nombre del sitio del archivo.
@@ -97,72 +228,729 @@ pub fn generate_result_data_from_line(
 /// # Returns
 ///
 /// El nombre del sitio extraído del nombre del archivo.
-fn get_site_name(path: &Path) -> String {
+pub(crate) fn get_site_name(path: &Path) -> String {
     path.file_name()
-        .map(|p| p.to_string_lossy().trim_end_matches(".jsonl").to_string())
+        .map(|p| {
+            p.to_string_lossy()
+                .trim_end_matches(GZIP_EXTENSION)
+                .trim_end_matches(".jsonl")
+                .to_string()
+        })
         .unwrap_or_else(|| {
             eprintln!("Error al obtener el nombre del archivo.");
             process::exit(1);
         })
 }
 
-/// Procesa las líneas del archivo y genera un `ResultData`.
+#[cfg(feature = "simd")]
+thread_local! {
+    /// Copia de la línea sobre la que simd-json parsea en el lugar.
+    static SIMD_BUFFER: std::cell::RefCell<Vec<u8>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Parsea una línea con la implementación indicada y pasa el resultado a `f`.
+///
+/// simd-json parsea sobre un buffer mutable, por lo que la línea se copia a un buffer del
+/// hilo. La estructura parseada toma prestado de ese buffer y solo es válida dentro de `f`.
 ///
 /// # Arguments
 ///
-/// * `reader` - `BufReader` para leer el archivo.
-/// * `site_name` - Nombre del sitio del archivo.
+/// * `line` - Bytes de la línea del archivo, sin el salto de línea.
+/// * `parser` - Implementación con la que se parsea la línea.
+/// * `f` - Función que recibe la línea parseada o el mensaje de error.
+fn with_parsed_line<F>(line: &[u8], parser: Parser, f: F)
+where
+    F: FnOnce(Result<LineJsonStructure, String>),
+{
+    match parser {
+        Parser::Serde => f(from_slice::<LineJsonStructure>(line).map_err(|e| e.to_string())),
+        #[cfg(feature = "simd")]
+        Parser::Simd => SIMD_BUFFER.with(|buffer| {
+            let mut buffer = buffer.borrow_mut();
+            buffer.clear();
+            buffer.extend_from_slice(line);
+            f(
+                simd_json::serde::from_slice::<LineJsonStructure>(&mut buffer)
+                    .map_err(|e| e.to_string()),
+            )
+        }),
+        #[cfg(not(feature = "simd"))]
+        Parser::Simd => unreachable!("--parser simd requiere la feature simd"),
+    }
+}
+
+/// Procesa una línea del archivo y la agrega al resultado parcial del worker.
+///
+/// # Arguments
+///
+/// * `partial` - Resultado parcial acumulado por el worker y sus métricas.
+/// * `line` - Bytes de la línea del archivo, sin el salto de línea.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar, si está habilitado.
 ///
 /// # Returns
 ///
-/// Un `ResultData` generado a partir del procesamiento de las líneas del archivo.
-fn process_lines(reader: BufReader<File>, site_name: &str) -> ResultData {
-    reader
+/// El resultado parcial con la información y las métricas de la línea. Si la línea no es un
+/// JSON válido, solo se suman sus métricas.
+fn process_line(
+    (mut accumulator, mut stats): (Accumulator, FileStats),
+    line: &[u8],
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> (Accumulator, FileStats) {
+    let bytes = line.len() as u64 + 1;
+    if let Some(progress) = progress {
+        progress.add_line(bytes);
+    }
+
+    let parse_start = options.timed.then(Instant::now);
+    with_parsed_line(line, options.parser, |parsed| {
+        let aggregation_start = parse_start.map(|start| {
+            stats.parse_ns += elapsed_ns(start);
+            Instant::now()
+        });
+        match parsed {
+            Ok(data) => add_line(&mut accumulator, data, site),
+            Err(e) => eprintln!("Error al analizar JSON en línea del archivo: {}", e),
+        }
+        if let Some(start) = aggregation_start {
+            stats.aggregation_ns += elapsed_ns(start);
+        }
+    });
+    stats.bytes += bytes;
+    stats.lines += 1;
+    (accumulator, stats)
+}
+
+/// Devuelve un resultado parcial vacío, utilizado como identidad al reducir.
+pub(crate) fn empty_partial() -> (Accumulator, FileStats) {
+    (Accumulator::default(), FileStats::default())
+}
+
+/// Combina dos resultados parciales, sumando el tiempo del merge al de agregación.
+///
+/// # Arguments
+///
+/// * `a` - Primer resultado parcial.
+/// * `b` - Segundo resultado parcial.
+pub(crate) fn combine_partials(
+    (acc, acc_stats): (Accumulator, FileStats),
+    (b, b_stats): (Accumulator, FileStats),
+) -> (Accumulator, FileStats) {
+    let aggregation_start = Instant::now();
+    let combined = acc + b;
+    let mut stats = acc_stats + b_stats;
+    stats.aggregation_ns += elapsed_ns(aggregation_start);
+    (combined, stats)
+}
+
+/// Devuelve el estado de un archivo según si su lectura fue interrumpida.
+///
+/// # Arguments
+///
+/// * `interrupted` - Si se dejó de leer por una cancelación.
+pub(crate) fn status_from(interrupted: bool) -> FileStatus {
+    if interrupted {
+        FileStatus::Partial
+    } else {
+        FileStatus::Complete
+    }
+}
+
+/// Divide un archivo en rangos de bytes alineados a saltos de línea, de aproximadamente
+/// `chunk_size` bytes cada uno.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `chunk_size` - Tamaño aproximado de cada rango.
+///
+/// # Returns
+///
+/// Los rangos que cubren todo el archivo, en orden. Cada rango comienza al inicio de una línea.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer el archivo.
+pub fn split_ranges(path: &Path, chunk_size: u64) -> io::Result<Vec<ByteRange>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let chunk_size = chunk_size.max(1);
+    let mut ranges = vec![];
+    let mut start = 0;
+
+    while start < size {
+        let target = start + chunk_size;
+        let end = if target >= size {
+            size
+        } else {
+            file.seek(SeekFrom::Start(target))?;
+            let mut reader = BufReader::new(&mut file);
+            let mut rest_of_line = vec![];
+            let read = reader.read_until(b'\n', &mut rest_of_line)?;
+            (target + read as u64).min(size)
+        };
+        ranges.push(ByteRange { start, end });
+        start = end;
+    }
+    Ok(ranges)
+}
+
+/// Procesa secuencialmente las líneas de un rango de bytes de un archivo.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `range` - Rango a procesar. Debe comenzar al inicio de una línea.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+///
+/// # Returns
+///
+/// Un `Accumulator` con las líneas del rango, sus métricas y si la lectura fue interrumpida.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir el archivo.
+pub fn process_range(
+    path: &Path,
+    range: ByteRange,
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> io::Result<(Accumulator, FileStats, bool)> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    let reader = BufReader::new(file.take(range.end - range.start));
+    Ok(process_reader(reader, site, options, progress))
+}
+
+/// Procesa secuencialmente las líneas de un lector. Igual que `process_block`, quita el `\r`
+/// final de cada línea y omite las líneas vacías.
+///
+/// # Arguments
+///
+/// * `reader` - Lector con buffer.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+///
+/// # Returns
+///
+/// Un `Accumulator` con las líneas leídas, sus métricas y si la lectura fue interrumpida.
+pub(crate) fn process_reader<R: BufRead>(
+    reader: R,
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> (Accumulator, FileStats, bool) {
+    let mut interrupted = false;
+    let (accumulator, stats) = reader
         .lines()
-        .par_bridge()
-        .filter_map(|line_result| {
-            let line = match line_result {
-                Ok(line) => line,
-                Err(e) => {
-                    eprintln!("Error al leer línea del archivo: {}", e);
-                    return None;
+        .take_while(|_| {
+            interrupted = is_cancelled();
+            !interrupted
+        })
+        .filter_map(|line_result| match line_result {
+            Ok(line) => Some(line),
+            Err(e) => {
+                eprintln!("Error al leer línea del archivo: {}", e);
+                None
+            }
+        })
+        .filter(|line| !line.is_empty())
+        .fold(empty_partial(), |partial, line| {
+            process_line(partial, line.as_bytes(), site, options, progress)
+        });
+    (accumulator, stats, interrupted)
+}
+
+/// Procesa un archivo grande dividiéndolo en rangos de bytes que se leen y procesan en paralelo.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `chunk_size` - Tamaño aproximado de cada rango.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+///
+/// # Returns
+///
+/// Un `Accumulator` generado a partir de todos los rangos, las métricas del procesamiento y el
+/// estado en el que quedó el archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer el archivo.
+fn process_chunked(
+    path: &Path,
+    chunk_size: u64,
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> io::Result<(Accumulator, FileStats, FileStatus)> {
+    let ranges = split_ranges(path, chunk_size)?;
+    let (accumulator, stats, interrupted) = ranges
+        .into_par_iter()
+        .map(|range| process_range(path, range, site, options, progress))
+        .try_reduce(
+            || (Accumulator::default(), FileStats::default(), false),
+            |(a, a_stats, a_interrupted), (b, b_stats, b_interrupted)| {
+                let (combined, stats) = combine_partials((a, a_stats), (b, b_stats));
+                Ok((combined, stats, a_interrupted || b_interrupted))
+            },
+        )?;
+    Ok((accumulator, stats, status_from(interrupted)))
+}
+
+/// Calcula los nanosegundos transcurridos desde un instante.
+///
+/// # Arguments
+///
+/// * `start` - Instante inicial.
+pub(crate) fn elapsed_ns(start: Instant) -> u64 {
+    start.elapsed().as_nanos() as u64
+}
+
+/// Divide un slice en bloques de aproximadamente `block_size` bytes que terminan en un salto
+/// de línea (o en el final del slice).
+///
+/// # Arguments
+///
+/// * `data` - Contenido a dividir.
+/// * `block_size` - Tamaño aproximado de cada bloque.
+fn split_blocks(data: &[u8], block_size: usize) -> Vec<&[u8]> {
+    let mut blocks = vec![];
+    let mut start = 0;
+    while start < data.len() {
+        let target = (start + block_size.max(1)).min(data.len());
+        let end = data[target..]
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(data.len(), |newline| target + newline + 1);
+        blocks.push(&data[start..end]);
+        start = end;
+    }
+    blocks
+}
+
+/// Procesa un archivo mapeado en memoria, recorriendo las líneas directamente sobre el mapeo
+/// en bloques que se procesan en paralelo, sin copiarlas a `String`s.
+///
+/// El archivo no se puede truncar mientras se procesa: el proceso terminaría con SIGBUS. Para
+/// entradas que pueden cambiar durante la corrida hay que usar `--no-mmap`.
+///
+/// # Arguments
+///
+/// * `file` - Archivo regular a mapear.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+///
+/// # Returns
+///
+/// Un `Accumulator` generado a partir de las líneas del archivo, las métricas del
+/// procesamiento y el estado en el que quedó el archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede mapear el archivo.
+fn process_mapped(
+    file: &File,
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> io::Result<(Accumulator, FileStats, FileStatus)> {
+    if file.metadata()?.len() == 0 {
+        let (data, stats) = empty_partial();
+        return Ok((data, stats, FileStatus::Complete));
+    }
+    // SAFETY: el archivo se abre solo para lectura y se asume que no se trunca mientras se
+    // procesa: acceder a una página del mapeo que quedó fuera del archivo produce SIGBUS, no un
+    // error de lectura. Por eso `watch`, donde los archivos cambian durante la corrida, los lee
+    // siempre con buffer.
+    let mmap = unsafe { Mmap::map(file)? };
+
+    let interrupted = AtomicBool::new(false);
+    let (accumulator, stats) = split_blocks(&mmap, MMAP_BLOCK_SIZE)
+        .into_par_iter()
+        .map(|block| {
+            let (accumulator, stats, block_interrupted) =
+                process_block(block, site, options, progress);
+            if block_interrupted {
+                interrupted.store(true, Ordering::Relaxed);
+            }
+            (accumulator, stats)
+        })
+        .reduce(empty_partial, combine_partials);
+    Ok((accumulator, stats, status_from(interrupted.into_inner())))
+}
+
+/// Procesa secuencialmente las líneas de un bloque de bytes en memoria.
+///
+/// # Arguments
+///
+/// * `block` - Líneas completas separadas por saltos de línea.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+///
+/// # Returns
+///
+/// Un `Accumulator` con las líneas del bloque, sus métricas y si se dejó de procesar por una
+/// cancelación.
+pub(crate) fn process_block(
+    block: &[u8],
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> (Accumulator, FileStats, bool) {
+    let mut interrupted = false;
+    let (accumulator, stats) = block
+        .split(|byte| *byte == b'\n')
+        .take_while(|_| {
+            interrupted = is_cancelled();
+            !interrupted
+        })
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .fold(empty_partial(), |partial, line| {
+            process_line(partial, line, site, options, progress)
+        });
+    (accumulator, stats, interrupted)
+}
+
+/// Indica si un archivo está comprimido con gzip, según su extensión.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+pub(crate) fn is_gzip(path: &Path) -> bool {
+    path.to_string_lossy().ends_with(GZIP_EXTENSION)
+}
+
+/// Abre un archivo para leerlo línea por línea, descomprimiéndolo si es gzip.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir el archivo.
+pub(crate) fn open_lines(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)?;
+    if is_gzip(path) {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Procesa las líneas de un lector en lotes que se parsean en paralelo, reservando cada lote
+/// en el presupuesto de memoria antes de leerlo. Si el presupuesto está lleno, la lectura
+/// espera a que se procesen lotes anteriores. Una línea que no entra en el lote pasa al
+/// siguiente, así los lotes solo superan la reserva si tienen una sola línea más larga.
+///
+/// La lectura queda en el hilo actual, normalmente un worker del pool de lectura, y los lotes
+/// se entregan al pool de parseo.
+///
+/// # Arguments
+///
+/// * `reader` - Lector con buffer del archivo.
+/// * `budget` - Presupuesto de memoria, compartido por todos los archivos con
+///   `--memory-budget` o propio del archivo si no.
+/// * `pool` - ThreadPool donde se parsean los lotes.
+/// * `site` - Identificador del sitio del archivo.
+/// * `options` - Parser de cada línea y si se miden sus tiempos.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+///
+/// # Returns
+///
+/// Un `Accumulator` generado a partir de las líneas del archivo, las métricas del
+/// procesamiento y el estado en el que quedó el archivo.
+fn process_budgeted<R: BufRead + Send>(
+    mut reader: R,
+    budget: &MemoryBudget,
+    pool: &ThreadPool,
+    site: Id,
+    options: LineOptions,
+    progress: Option<&Progress>,
+) -> (Accumulator, FileStats, FileStatus) {
+    let batch_size = budget.batch_size(pool.current_num_threads());
+    let partial = Mutex::new(empty_partial());
+    let interrupted = AtomicBool::new(false);
+    pool.in_place_scope(|scope| {
+        let mut eof = false;
+        // línea leída que no entraba en el lote anterior
+        let mut carry = Vec::new();
+        while !eof || !carry.is_empty() {
+            if is_cancelled() {
+                interrupted.store(true, Ordering::Relaxed);
+                break;
+            }
+            let mut permit = budget.acquire(batch_size as u64);
+            let mut lines = Vec::with_capacity(batch_size);
+            lines.append(&mut carry);
+            while !eof && carry.is_empty() && lines.len() < batch_size {
+                let start = lines.len();
+                match reader.read_until(b'\n', &mut lines) {
+                    Ok(0) => eof = true,
+                    Ok(_) if start > 0 && lines.len() > batch_size => {
+                        carry = lines.split_off(start);
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        eprintln!("Error al leer línea del archivo: {}", e);
+                        eof = true;
+                    }
                 }
-            };
-
-            match from_str::<LineJsonStructure>(&line) {
-                Ok(data) => Some(generate_result_data_from_line(
-                    data,
-                    JsonStructure::new(site_name.to_string()),
-                )),
-                Err(e) => {
-                    eprintln!("Error al analizar JSON en línea del archivo: {}", e);
-                    Some(ResultData::new(PADRON, HashMap::new(), HashMap::new()))
+            }
+            if lines.is_empty() {
+                break;
+            }
+            // solo una línea más larga que el lote puede agrandar el buffer, se ajusta la
+            // reserva a lo leído
+            lines.shrink_to_fit();
+            permit.resize(lines.len() as u64);
+            let (partial, interrupted) = (&partial, &interrupted);
+            scope.spawn(move |_| {
+                let (accumulator, stats, block_interrupted) =
+                    process_block(&lines, site, options, progress);
+                drop((lines, permit));
+                if block_interrupted {
+                    interrupted.store(true, Ordering::Relaxed);
                 }
+                let mut partial = partial.lock().unwrap_or_else(|e| e.into_inner());
+                *partial = combine_partials(std::mem::take(&mut *partial), (accumulator, stats));
+            });
+        }
+    });
+    let (accumulator, stats) = partial.into_inner().unwrap_or_else(|e| e.into_inner());
+    (accumulator, stats, status_from(interrupted.into_inner()))
+}
+
+/// Procesa el contenido de un archivo abierto eligiendo la forma de lectura.
+///
+/// Los archivos comprimidos y los que no son archivos regulares (por ejemplo pipes) se leen
+/// con buffer. Los archivos regulares se mapean en memoria, salvo que se desactive con
+/// `--no-mmap`, en cuyo caso los más grandes que el tamaño de chunk se dividen en rangos. Con
+/// un presupuesto de memoria todos se leen con buffer en lotes acotados por el presupuesto.
+///
+/// La lectura con buffer se hace en el hilo que llama, un worker del pool de lectura, y los
+/// lotes se parsean en el pool de parseo. Los archivos mapeados y los divididos en rangos se
+/// leen dentro del pool de parseo, porque cada bloque se lee (por fallos de página o con
+/// `seek`) en el mismo worker que lo parsea; el worker de lectura solo espera el resultado.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `file` - Archivo abierto.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida.
+/// * `site` - Identificador del sitio del archivo.
+/// * `progress` - Progreso a actualizar por cada línea, si está habilitado.
+/// * `budget` - Presupuesto de memoria, si está habilitado.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede leer el archivo.
+fn process_opened(
+    path: &Path,
+    file: File,
+    config: &Config,
+    pools: &Pools,
+    site: Id,
+    progress: Option<&Progress>,
+    budget: Option<&MemoryBudget>,
+) -> io::Result<(Accumulator, FileStats, FileStatus)> {
+    let metadata = file.metadata()?;
+    let options = LineOptions::from(config);
+    let buffered = budget.is_some()
+        || is_gzip(path)
+        || !metadata.is_file()
+        || (config.no_mmap && metadata.len() <= config.chunk_size());
+    if buffered {
+        let reader: Box<dyn BufRead + Send> = if is_gzip(path) {
+            Box::new(BufReader::new(GzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        let file_budget;
+        let budget = match budget {
+            Some(budget) => budget,
+            None => {
+                file_budget = MemoryBudget::for_workers(pools.parse().current_num_threads());
+                &file_budget
             }
-        })
-        .reduce(
-            || ResultData::new(PADRON, HashMap::new(), HashMap::new()),
-            |acc, b| acc + b,
-        )
+        };
+        return Ok(process_budgeted(
+            reader,
+            budget,
+            pools.parse(),
+            site,
+            options,
+            progress,
+        ));
+    }
+    pools.parse().install(|| {
+        if config.no_mmap {
+            process_chunked(path, config.chunk_size(), site, options, progress)
+        } else {
+            process_mapped(&file, site, options, progress)
+        }
+    })
 }
 
-/// Procesa un archivo individual y genera un `ResultData`.
+/// Procesa un archivo individual y genera un `FileResult`.
 ///
 /// # Arguments
 ///
 /// * `path` - Ruta del archivo a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida.
+/// * `progress` - Progreso a actualizar, si está habilitado.
+/// * `budget` - Presupuesto de memoria, si está habilitado.
 ///
 /// # Returns
 ///
-/// Un `ResultData` generado a partir del procesamiento del archivo.
-pub fn process_file(path: &PathBuf) -> ResultData {
-    match File::open(path) {
-        Ok(file) => {
-            let reader = BufReader::new(file);
-            let site_name = get_site_name(path);
+/// Un `FileResult` con el `ResultData` del archivo, sus métricas y su estado. Si el
+/// procesamiento fue cancelado antes de empezar el archivo, queda como `NotProcessed`.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir o leer el archivo.
+pub(crate) fn process_file(
+    path: &PathBuf,
+    config: &Config,
+    pools: &Pools,
+    progress: Option<&Progress>,
+    budget: Option<&MemoryBudget>,
+) -> io::Result<FileResult> {
+    if is_cancelled() {
+        return Ok(not_processed(path));
+    }
 
-            process_lines(reader, &site_name)
-        }
+    let start = Instant::now();
+    let site_name = get_site_name(path);
+    if let Some(progress) = progress {
+        progress.start_file(&site_name);
+    }
+
+    let site = intern(&site_name);
+    let processed = File::open(path)
+        .and_then(|file| process_opened(path, file, config, pools, site, progress, budget));
+    if let Some(progress) = progress {
+        progress.finish_file();
+    }
+    let (accumulator, stats, status) = processed?;
+
+    let mut result = processed_result(path, accumulator, stats, status);
+    result.stats.elapsed_ns = elapsed_ns(start);
+    Ok(result)
+}
+
+/// Genera el `FileResult` de un archivo que no se llegó a procesar.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+pub(crate) fn not_processed(path: &Path) -> FileResult {
+    FileResult {
+        path: path.to_path_buf(),
+        status: FileStatus::NotProcessed,
+        source: FileSource::Processed,
+        stats: FileStats::default(),
+        data: Accumulator::default(),
+    }
+}
+
+/// Genera el `FileResult` de un archivo procesado a partir de su `Accumulator`. Los nombres se
+/// resuelven recién al combinar los resultados de todos los archivos.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo.
+/// * `accumulator` - `Accumulator` con todas las líneas procesadas del archivo.
+/// * `stats` - Métricas del procesamiento.
+/// * `status` - Estado en el que quedó el archivo.
+pub(crate) fn processed_result(
+    path: &Path,
+    accumulator: Accumulator,
+    stats: FileStats,
+    status: FileStatus,
+) -> FileResult {
+    FileResult {
+        path: path.to_path_buf(),
+        status,
+        source: FileSource::Processed,
+        stats,
+        data: accumulator,
+    }
+}
+
+/// Genera el detalle de archivos procesados para un resultado parcial.
+///
+/// # Arguments
+///
+/// * `results` - Resultados por archivo.
+///
+/// # Returns
+///
+/// `None` si todos los archivos se procesaron por completo, o un `IncompleteData` con el
+/// estado de cada archivo en caso contrario.
+pub(crate) fn incomplete_data(results: &[FileResult]) -> Option<IncompleteData> {
+    if results.iter().all(|r| r.status == FileStatus::Complete) {
+        return None;
+    }
+
+    let files_with = |status: FileStatus| -> Vec<String> {
+        results
+            .iter()
+            .filter(|r| r.status == status)
+            .map(|r| r.path.display().to_string())
+            .collect()
+    };
+
+    Some(IncompleteData {
+        complete_files: files_with(FileStatus::Complete),
+        partial_files: files_with(FileStatus::Partial),
+        not_processed_files: files_with(FileStatus::NotProcessed),
+    })
+}
+
+/// Procesa un archivo utilizando los checkpoints y el cache, si están configurados.
+///
+/// Con `resume`, si existe un checkpoint cuyo tamaño, fecha de modificación y hash SHA-256
+/// coinciden con los del archivo actual se reutiliza sin volver a procesarlo. Si no, se busca
+/// el resultado en el cache por el mismo hash, que se calcula una sola vez. Cada archivo
+/// procesado por completo guarda su checkpoint y su entrada de cache.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida.
+/// * `progress` - Progreso a actualizar, si está habilitado.
+/// * `budget` - Presupuesto de memoria, si está habilitado.
+///
+/// # Returns
+///
+/// Un `FileResult` con el `ResultData` del archivo y su estado. Si no puede abrir o leer el
+/// archivo, termina el proceso.
+pub fn process_file_with_state(
+    path: &PathBuf,
+    config: &Config,
+    pools: &Pools,
+    progress: Option<&Progress>,
+    budget: Option<&MemoryBudget>,
+) -> FileResult {
+    match try_process_file_with_state(path, config, pools, progress, budget) {
+        Ok(result) => result,
         Err(e) => {
             eprintln!("Error al abrir archivo {}: {}", path.display(), e);
             process::exit(1);
@@ -170,21 +958,300 @@ pub fn process_file(path: &PathBuf) -> ResultData {
     }
 }
 
-/// Procesa una lista de archivos y devuelve un `ResultData` combinado.
+/// Igual que `process_file_with_state`, pero devuelve el error si no puede abrir o leer el
+/// archivo en lugar de terminar el proceso. Lo usan los modos que no deben terminar por un
+/// archivo que desaparece o no se puede leer, como `watch` o los workers.
+///
+/// # Arguments
+///
+/// * `path` - Ruta del archivo a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida.
+/// * `progress` - Progreso a actualizar, si está habilitado.
+/// * `budget` - Presupuesto de memoria, si está habilitado.
+///
+/// # Returns
+///
+/// Un `FileResult` con el `ResultData` del archivo y su estado.
+///
+/// # Errors
+///
+/// Devuelve un error si no puede abrir o leer el archivo.
+pub(crate) fn try_process_file_with_state(
+    path: &PathBuf,
+    config: &Config,
+    pools: &Pools,
+    progress: Option<&Progress>,
+    budget: Option<&MemoryBudget>,
+) -> io::Result<FileResult> {
+    if config.state_dir.is_none() && config.cache_dir.is_none() {
+        return process_file(path, config, pools, progress, budget);
+    }
+
+    let fingerprint = match checkpoint::fingerprint(path) {
+        Ok(fingerprint) => fingerprint,
+        Err(e) => {
+            eprintln!(
+                "Error al calcular el fingerprint de {}: {}",
+                path.display(),
+                e
+            );
+            return process_file(path, config, pools, progress, budget);
+        }
+    };
+    let hash = match checkpoint::hash_file(path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!("Error al calcular el hash de {}: {}", path.display(), e);
+            return process_file(path, config, pools, progress, budget);
+        }
+    };
+    let site_name = get_site_name(path);
+    let complete = |data: ResultData, source: FileSource| {
+        if let Some(progress) = progress {
+            progress.skip_file(fingerprint.size);
+        }
+        FileResult {
+            path: path.clone(),
+            status: FileStatus::Complete,
+            source,
+            stats: FileStats {
+                bytes: fingerprint.size,
+                ..FileStats::default()
+            },
+            data: Accumulator::from_result_data(data),
+        }
+    };
+
+    if let (Some(state_dir), true) = (&config.state_dir, config.resume) {
+        if let Some(data) = checkpoint::load(state_dir, path, &fingerprint, &hash) {
+            eprintln!("Se reutiliza el checkpoint de {}", path.display());
+            return Ok(complete(data, FileSource::Checkpoint));
+        }
+    }
+
+    if let Some(cache_dir) = &config.cache_dir {
+        if let Some(data) = cache::load(cache_dir, &hash, &site_name, PADRON) {
+            eprintln!("Se reutiliza el cache de {}", path.display());
+            return Ok(complete(data, FileSource::Cache));
+        }
+    }
+
+    let result = process_file(path, config, pools, progress, budget)?;
+    if result.status != FileStatus::Complete {
+        return Ok(result);
+    }
+    let data = result.data.clone().into_result_data(PADRON);
+    if let Some(state_dir) = &config.state_dir {
+        if let Err(e) = checkpoint::save(state_dir, path, &fingerprint, &hash, &data) {
+            eprintln!(
+                "Error al guardar el checkpoint de {}: {}",
+                path.display(),
+                e
+            );
+        }
+    }
+    if let Some(cache_dir) = &config.cache_dir {
+        if let Err(e) = cache::save(cache_dir, &hash, &site_name, &data) {
+            eprintln!("Error al guardar el cache de {}: {}", path.display(), e);
+        }
+    }
+    Ok(result)
+}
+
+/// Combina los resultados de los archivos con un merge en árbol: rayon suma de a pares los
+/// `Accumulator`s en paralelo hasta obtener uno solo, y recién entonces se resuelven los
+/// nombres de sitios y etiquetas.
+///
+/// # Arguments
+///
+/// * `results` - Resultados de cada archivo.
+///
+/// # Returns
+///
+/// Un `ResultData` con la suma de todos los resultados.
+pub(crate) fn merge_results(results: Vec<FileResult>) -> ResultData {
+    results
+        .into_par_iter()
+        .map(|result| result.data)
+        .reduce(Accumulator::default, |a, b| a + b)
+        .into_result_data(PADRON)
+}
+
+/// Ordena los archivos de mayor a menor tamaño. Al tomarlos en este orden, cada worker que
+/// queda libre toma el archivo más grande pendiente y los últimos en empezar son los chicos.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+///
+/// # Returns
+///
+/// Los archivos ordenados junto con su posición en `paths`.
+pub(crate) fn schedule_largest_first(paths: &[PathBuf]) -> Vec<(usize, &PathBuf)> {
+    let mut sized: Vec<(u64, usize, &PathBuf)> = paths
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let size = path.metadata().map_or(0, |metadata| metadata.len());
+            (size, index, path)
+        })
+        .collect();
+    sized.sort_by_key(|(size, index, _)| (Reverse(*size), *index));
+    sized
+        .into_iter()
+        .map(|(_, index, path)| (index, path))
+        .collect()
+}
+
+/// Reparte elementos en grupos de tamaño parecido. Cada elemento, de mayor a menor, se asigna al
+/// grupo con menos bytes hasta el momento.
+///
+/// # Arguments
+///
+/// * `sizes` - Tamaño de cada elemento.
+/// * `groups` - Cantidad de grupos.
+///
+/// # Returns
+///
+/// Las posiciones en `sizes` de los elementos de cada grupo, de mayor a menor tamaño.
+pub(crate) fn balance(sizes: &[u64], groups: usize) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| (Reverse(sizes[index]), index));
+    let mut assigned: Vec<(u64, Vec<usize>)> = vec![(0, Vec::new()); groups.max(1)];
+    for index in order {
+        if let Some((bytes, members)) = assigned.iter_mut().min_by_key(|(bytes, _)| *bytes) {
+            *bytes += sizes[index];
+            members.push(index);
+        }
+    }
+    assigned.into_iter().map(|(_, members)| members).collect()
+}
+
+/// Informa que un backend alternativo no pudo abrir un archivo de entrada y termina el programa,
+/// igual que el backend de rayon. Se llama desde el hilo que inició el procesamiento, una vez
+/// que terminaron los hilos del backend.
+///
+/// # Arguments
+///
+/// * `error` - Error al abrir el archivo, con su ruta.
+fn exit_backend_error(error: io::Error) -> ! {
+    eprintln!("Error al abrir archivo {}", error);
+    std::process::exit(1);
+}
+
+/// Procesa una lista de archivos según la configuración y devuelve un `ResultData` combinado.
+///
+/// Si el procesamiento fue cancelado, el resultado queda marcado como incompleto. Si
+/// `config.progress` está habilitado, se muestra el progreso por stderr.
 ///
 /// # Arguments
 ///
 /// * `paths` - Vector de rutas de archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools de la corrida: los archivos se recorren en el de lectura y las
+///   líneas se parsean en el de parseo.
+/// * `report` - Reporte de performance donde se registran las métricas por archivo y los
+///   tiempos de procesamiento y merge, si está habilitado.
 ///
 /// # Returns
 ///
 /// Un `ResultData` combinado a partir del procesamiento de los archivos.
-pub fn process_files(paths: &[PathBuf]) -> ResultData {
-    let results_per_file: Vec<ResultData> = paths.par_iter().map(process_file).collect();
+pub fn process_files(
+    paths: &[PathBuf],
+    config: &Config,
+    pools: &Pools,
+    report: Option<&mut PerfReport>,
+) -> ResultData {
+    let _session = Session::start();
+    let processing_start = Instant::now();
+    let workers = pools
+        .io()
+        .current_num_threads()
+        .max(pools.parse().current_num_threads());
+    let progress = config
+        .progress
+        .then(|| Arc::new(Progress::new(paths, workers)));
+    let reporter = progress.clone().map(ProgressReporter::start);
+    let budget = config.memory_budget.map(MemoryBudget::new);
+
+    let process = |(index, path): (usize, &PathBuf)| {
+        let start = processing_start.elapsed();
+        let result =
+            process_file_with_state(path, config, pools, progress.as_deref(), budget.as_ref());
+        (index, start, processing_start.elapsed(), result)
+    };
+    let mut shard_reports: Vec<ShardReport> = Vec::new();
+    let mut timed_results: Vec<TimedResult> = match (config.backend, config.schedule) {
+        (Backend::Sharded, _) => {
+            let (timed_results, reports) = pools
+                .parse()
+                .install(|| sharded::process(paths, config, processing_start))
+                .unwrap_or_else(|e| exit_backend_error(e));
+            shard_reports = reports;
+            timed_results
+        }
+        (Backend::Async, _) => async_io::process(paths, config, pools.parse(), processing_start)
+            .unwrap_or_else(|e| exit_backend_error(e)),
+        (Backend::Pipeline, _) => pools
+            .parse()
+            .install(|| pipeline::process(paths, config, processing_start))
+            .unwrap_or_else(|e| exit_backend_error(e)),
+        (Backend::Threads, _) => pools
+            .parse()
+            .install(|| threads::process(paths, config, processing_start))
+            .unwrap_or_else(|e| exit_backend_error(e)),
+        (Backend::Actors, _) => pools
+            .parse()
+            .install(|| actors::process(paths, config, processing_start))
+            .unwrap_or_else(|e| exit_backend_error(e)),
+        (Backend::Rayon, Schedule::Largest) => pools.io().install(|| {
+            schedule_largest_first(paths)
+                .into_iter()
+                .par_bridge()
+                .map(process)
+                .collect()
+        }),
+        (Backend::Rayon, Schedule::Input) => pools
+            .io()
+            .install(|| paths.par_iter().enumerate().map(process).collect()),
+    };
+    timed_results.sort_by_key(|(index, ..)| *index);
+    if let Some(reporter) = reporter {
+        reporter.stop();
+    }
+    let processing = processing_start.elapsed();
+    let last_start = timed_results
+        .iter()
+        .map(|(_, start, ..)| *start)
+        .max()
+        .unwrap_or_default();
+
+    let file_reports: Option<Vec<FileReport>> = report.is_some().then(|| {
+        timed_results
+            .iter()
+            .map(|(_, start, finish, result)| FileReport::new(result, *start, *finish))
+            .collect()
+    });
+    let results_per_file: Vec<FileResult> = timed_results
+        .into_iter()
+        .map(|(.., result)| result)
+        .collect();
+    let incomplete = incomplete_data(&results_per_file);
+
+    let merging_start = Instant::now();
+    let mut combined_result = pools.parse().install(|| merge_results(results_per_file));
+    combined_result.incomplete = incomplete;
 
-    let mut combined_result = ResultData::new(PADRON, HashMap::new(), HashMap::new());
-    for result in results_per_file {
-        combined_result = combined_result + result;
+    if let (Some(report), Some(file_reports)) = (report, file_reports) {
+        report.files = file_reports;
+        report.backend = config.backend;
+        report.schedule = config.schedule;
+        report.tail_ms = as_ms(processing.saturating_sub(last_start));
+        report.shards = shard_reports;
+        report.budget = budget.as_ref().map(MemoryBudget::report);
+        report.phases.processing_ms = as_ms(processing);
+        report.phases.merging_ms = as_ms(merging_start.elapsed());
     }
     combined_result
 }
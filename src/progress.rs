@@ -0,0 +1,291 @@
+//! Este módulo muestra el progreso del procesamiento por stderr.
+//!
+//! Si stderr es una terminal, se redibuja un resumen varias veces por segundo. Si no, se
+//! imprime una línea de log periódica.
+
+use std::fs;
+use std::io::{stderr, IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Intervalo de actualización cuando stderr es una terminal.
+const TTY_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Intervalo entre líneas de log cuando stderr no es una terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Granularidad con la que el reporter verifica si debe terminar.
+const STOP_POLL: Duration = Duration::from_millis(50);
+
+/// Bytes por MB.
+const MB: f64 = 1024.0 * 1024.0;
+
+/// WorkerCounters: Contadores de un worker. Se alinean a una línea de cache para que los
+/// workers no compitan al actualizarlos.
+#[derive(Default)]
+#[repr(align(64))]
+struct WorkerCounters {
+    lines: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// Progress: Estado compartido del progreso del procesamiento.
+pub(crate) struct Progress {
+    files_total: usize,
+    bytes_total: u64,
+    files_done: AtomicUsize,
+    bytes_skipped: AtomicU64,
+    /// Un contador por worker del pool, más uno para hilos fuera del pool.
+    workers: Vec<WorkerCounters>,
+    /// Archivo que comenzó a procesar cada worker.
+    current_files: Mutex<Vec<Option<String>>>,
+    start: Instant,
+}
+
+/// Snapshot: Valores del progreso en un instante.
+pub(crate) struct Snapshot {
+    pub(crate) elapsed: Duration,
+    pub(crate) lines: u64,
+    pub(crate) bytes: u64,
+    pub(crate) worker_lines: Vec<u64>,
+}
+
+impl Progress {
+    /// Crea una nueva instancia de `Progress` para los archivos a procesar.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Archivos a procesar.
+    /// * `workers` - Cantidad de workers de los ThreadPools.
+    ///
+    /// # Returns
+    ///
+    /// Una nueva instancia de `Progress` con los totales de archivos y bytes.
+    pub(crate) fn new(paths: &[PathBuf], workers: usize) -> Self {
+        let workers = workers + 1;
+        Progress {
+            files_total: paths.len(),
+            bytes_total: paths
+                .iter()
+                .filter_map(|path| fs::metadata(path).ok())
+                .map(|metadata| metadata.len())
+                .sum(),
+            files_done: AtomicUsize::new(0),
+            bytes_skipped: AtomicU64::new(0),
+            workers: (0..workers).map(|_| WorkerCounters::default()).collect(),
+            current_files: Mutex::new(vec![None; workers]),
+            start: Instant::now(),
+        }
+    }
+
+    /// Devuelve el índice de los contadores del worker actual.
+    fn worker_index(&self) -> usize {
+        rayon::current_thread_index()
+            .filter(|index| *index < self.workers.len() - 1)
+            .unwrap_or(self.workers.len() - 1)
+    }
+
+    /// Registra que el worker actual comenzó a procesar un archivo.
+    ///
+    /// # Arguments
+    ///
+    /// * `site` - Nombre del sitio del archivo.
+    pub(crate) fn start_file(&self, site: &str) {
+        let index = self.worker_index();
+        if let Ok(mut current_files) = self.current_files.lock() {
+            current_files[index] = Some(site.to_string());
+        }
+    }
+
+    /// Registra una línea procesada por el worker actual.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Bytes de la línea, incluyendo el salto de línea.
+    pub(crate) fn add_line(&self, bytes: u64) {
+        let counters = &self.workers[self.worker_index()];
+        counters.lines.fetch_add(1, Ordering::Relaxed);
+        counters.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Registra que el worker actual terminó de procesar el archivo que había comenzado.
+    pub(crate) fn finish_file(&self) {
+        let index = self.worker_index();
+        if let Ok(mut current_files) = self.current_files.lock() {
+            current_files[index] = None;
+        }
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Registra un archivo cuyo resultado se obtuvo sin procesarlo (checkpoint o cache).
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - Tamaño del archivo.
+    pub(crate) fn skip_file(&self, bytes: u64) {
+        self.bytes_skipped.fetch_add(bytes, Ordering::Relaxed);
+        self.files_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Toma los valores actuales de los contadores.
+    pub(crate) fn snapshot(&self) -> Snapshot {
+        let worker_lines: Vec<u64> = self
+            .workers
+            .iter()
+            .map(|counters| counters.lines.load(Ordering::Relaxed))
+            .collect();
+        Snapshot {
+            elapsed: self.start.elapsed(),
+            lines: worker_lines.iter().sum(),
+            bytes: self
+                .workers
+                .iter()
+                .map(|counters| counters.bytes.load(Ordering::Relaxed))
+                .sum(),
+            worker_lines,
+        }
+    }
+
+    /// Genera la línea de resumen del progreso.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - Valores actuales.
+    pub(crate) fn summary(&self, snapshot: &Snapshot) -> String {
+        let seconds = snapshot.elapsed.as_secs_f64().max(f64::EPSILON);
+        let bytes_done = snapshot.bytes + self.bytes_skipped.load(Ordering::Relaxed);
+        let bytes_rate = snapshot.bytes as f64 / seconds;
+        let eta = if bytes_rate > 0.0 {
+            let remaining = self.bytes_total.saturating_sub(bytes_done) as f64;
+            format!("{:.0}s", remaining / bytes_rate)
+        } else {
+            "-".to_string()
+        };
+        let percent = if self.bytes_total > 0 {
+            100.0 * bytes_done as f64 / self.bytes_total as f64
+        } else {
+            100.0
+        };
+        format!(
+            "Archivos {}/{} | {:.1}/{:.1} MB ({:.0}%) | {:.0} líneas/s | ETA {}",
+            self.files_done.load(Ordering::Relaxed),
+            self.files_total,
+            bytes_done as f64 / MB,
+            self.bytes_total as f64 / MB,
+            percent.min(100.0),
+            snapshot.lines as f64 / seconds,
+            eta
+        )
+    }
+
+    /// Genera la línea con la actividad de cada worker del pool desde el snapshot anterior.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - Valores actuales.
+    /// * `previous` - Valores del snapshot anterior.
+    pub(crate) fn workers_activity(&self, snapshot: &Snapshot, previous: &Snapshot) -> String {
+        let seconds = (snapshot.elapsed - previous.elapsed)
+            .as_secs_f64()
+            .max(f64::EPSILON);
+        let current_files = self
+            .current_files
+            .lock()
+            .map(|files| files.clone())
+            .unwrap_or_default();
+        let pool_workers = self.workers.len() - 1;
+        (0..pool_workers)
+            .map(|index| {
+                let rate =
+                    (snapshot.worker_lines[index] - previous.worker_lines[index]) as f64 / seconds;
+                let file = current_files[index].as_deref().unwrap_or("-");
+                format!("w{} {:.0}/s [{}]", index, rate, file)
+            })
+            .collect::<Vec<String>>()
+            .join(" | ")
+    }
+}
+
+/// ProgressReporter: Hilo que imprime el progreso hasta que se lo detiene.
+pub(crate) struct ProgressReporter {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ProgressReporter {
+    /// Lanza el hilo que imprime el progreso por stderr.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - Progreso compartido con los workers.
+    ///
+    /// # Returns
+    ///
+    /// Un `ProgressReporter` que detiene el hilo al llamar a `stop`.
+    pub(crate) fn start(progress: Arc<Progress>) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || report_loop(&progress, &thread_stop));
+        ProgressReporter {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    /// Detiene el hilo, que imprime el estado final antes de terminar.
+    pub(crate) fn stop(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Imprime el progreso periódicamente hasta que se indique que debe terminar.
+///
+/// # Arguments
+///
+/// * `progress` - Progreso compartido con los workers.
+/// * `stop` - Indica que el hilo debe terminar.
+fn report_loop(progress: &Progress, stop: &AtomicBool) {
+    let tty = stderr().is_terminal();
+    let interval = if tty { TTY_INTERVAL } else { LOG_INTERVAL };
+    let mut previous = progress.snapshot();
+    let mut drawn = false;
+    let mut last_report = Instant::now();
+
+    loop {
+        let stopping = stop.load(Ordering::SeqCst);
+        if !stopping && last_report.elapsed() < interval {
+            thread::sleep(STOP_POLL);
+            continue;
+        }
+        last_report = Instant::now();
+
+        let snapshot = progress.snapshot();
+        let summary = progress.summary(&snapshot);
+        let activity = progress.workers_activity(&snapshot, &previous);
+        let mut err = stderr().lock();
+        if tty {
+            if drawn {
+                let _ = write!(err, "\x1b[1A\r\x1b[2K");
+            }
+            let _ = write!(err, "\r\x1b[2K{}\n\x1b[2K{}", summary, activity);
+            if stopping {
+                let _ = writeln!(err);
+            }
+            let _ = err.flush();
+            drawn = true;
+        } else {
+            let _ = writeln!(err, "[progreso] {} | {}", summary, activity);
+        }
+        previous = snapshot;
+
+        if stopping {
+            break;
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Este módulo genera el reporte de performance por archivo y por fase del procesamiento.
+
+use crate::budget::BudgetReport;
+use crate::config::{Backend, Schedule};
+use crate::output::write_atomic;
+use crate::structs::{FileResult, FileSource, FileStats, FileStatus};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+/// Bytes por MB.
+const MB: f64 = 1024.0 * 1024.0;
+
+/// Nanosegundos por milisegundo.
+const NS_PER_MS: f64 = 1_000_000.0;
+
+/// FileReport: Métricas de un archivo de entrada.
+#[derive(Debug, Serialize)]
+pub(crate) struct FileReport {
+    path: String,
+    status: FileStatus,
+    source: FileSource,
+    bytes: u64,
+    lines: u64,
+    parse_ms: f64,
+    aggregation_ms: f64,
+    elapsed_ms: f64,
+    throughput_mb_s: f64,
+    /// Momento en que se empezó el archivo, desde el inicio del procesamiento.
+    start_ms: f64,
+    /// Momento en que se terminó el archivo, desde el inicio del procesamiento.
+    finish_ms: f64,
+}
+
+impl FileReport {
+    /// Crea el reporte de un archivo a partir de su resultado.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - Resultado del archivo.
+    /// * `start` - Momento en que se empezó el archivo, desde el inicio del procesamiento.
+    /// * `finish` - Momento en que se terminó el archivo, desde el inicio del procesamiento.
+    pub(crate) fn new(result: &FileResult, start: Duration, finish: Duration) -> Self {
+        let FileStats {
+            bytes,
+            lines,
+            parse_ns,
+            aggregation_ns,
+            elapsed_ns,
+        } = result.stats;
+        let throughput_mb_s = if elapsed_ns > 0 {
+            (bytes as f64 / MB) / (elapsed_ns as f64 / 1e9)
+        } else {
+            0.0
+        };
+        FileReport {
+            path: result.path.display().to_string(),
+            status: result.status,
+            source: result.source,
+            bytes,
+            lines,
+            parse_ms: parse_ns as f64 / NS_PER_MS,
+            aggregation_ms: aggregation_ns as f64 / NS_PER_MS,
+            elapsed_ms: elapsed_ns as f64 / NS_PER_MS,
+            throughput_mb_s,
+            start_ms: as_ms(start),
+            finish_ms: as_ms(finish),
+        }
+    }
+}
+
+/// ShardReport: Contención del lock de un shard del backend `sharded`.
+#[derive(Debug, Serialize)]
+pub(crate) struct ShardReport {
+    /// Cantidad de veces que se tomó el lock.
+    locks: u64,
+    /// Cantidad de veces que el lock estaba tomado por otro worker.
+    contended: u64,
+    /// Tiempo total de espera por el lock.
+    wait_ms: f64,
+}
+
+impl ShardReport {
+    /// Crea el reporte de un shard.
+    ///
+    /// # Arguments
+    ///
+    /// * `locks` - Cantidad de veces que se tomó el lock.
+    /// * `contended` - Cantidad de veces que hubo que esperar el lock.
+    /// * `wait_ns` - Tiempo total de espera, en nanosegundos.
+    pub(crate) fn new(locks: u64, contended: u64, wait_ns: u64) -> Self {
+        ShardReport {
+            locks,
+            contended,
+            wait_ms: wait_ns as f64 / NS_PER_MS,
+        }
+    }
+}
+
+/// PhaseTimes: Tiempo transcurrido en cada fase global, en milisegundos.
+#[derive(Debug, Serialize, Default)]
+pub(crate) struct PhaseTimes {
+    pub(crate) listing_ms: f64,
+    pub(crate) processing_ms: f64,
+    pub(crate) merging_ms: f64,
+    pub(crate) totals_ms: f64,
+    pub(crate) serialization_ms: f64,
+    pub(crate) total_ms: f64,
+}
+
+/// PerfReport: Reporte de performance de una corrida.
+#[derive(Debug, Serialize, Default)]
+pub(crate) struct PerfReport {
+    pub(crate) workers: usize,
+    pub(crate) backend: Backend,
+    pub(crate) schedule: Schedule,
+    pub(crate) phases: PhaseTimes,
+    /// Tiempo entre el inicio del último archivo y el final del procesamiento. Un valor alto
+    /// indica que un archivo grande quedó procesándose al final con pocos workers.
+    pub(crate) tail_ms: f64,
+    /// Máximo de memoria residente del proceso, si el sistema lo informa.
+    pub(crate) peak_rss_mb: Option<f64>,
+    /// Contención de cada shard, solo con `--backend sharded`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) shards: Vec<ShardReport>,
+    /// Uso del presupuesto de memoria, solo con `--memory-budget`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) budget: Option<BudgetReport>,
+    pub(crate) files: Vec<FileReport>,
+}
+
+/// Convierte una duración a milisegundos.
+///
+/// # Arguments
+///
+/// * `duration` - Duración a convertir.
+pub(crate) fn as_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Devuelve el máximo de memoria residente del proceso (VmHWM de `/proc/self/status`).
+///
+/// # Returns
+///
+/// La cantidad de MB, o `None` si el sistema no la informa.
+pub(crate) fn peak_rss_mb() -> Option<f64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some((kb * 1024) as f64 / MB)
+}
+
+impl PerfReport {
+    /// Escribe el reporte en formato JSON.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Archivo donde se escribe el reporte.
+    ///
+    /// # Errors
+    ///
+    /// Devuelve un error si no puede escribir el archivo.
+    pub(crate) fn write(&self, path: &Path) -> io::Result<()> {
+        let contents = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        write_atomic(path, &contents)
+    }
+}
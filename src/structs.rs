@@ -1,65 +1,103 @@
+use crate::intern::{intern, names, Id};
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
 
 /// Contiene el máximo número de etiquetas "chatty".
 pub(crate) const CHATTY_TAGS_MAX: usize = 10;
 
 /// LineJsonStructure: Contiene la información de una linea del json.
+///
+/// Los textos no se construyen: sus palabras se cuentan mientras se deserializan. Las
+/// etiquetas se toman prestadas de la línea siempre que no tengan caracteres escapados.
 
 #[derive(Debug, Deserialize)]
-pub(crate) struct LineJsonStructure {
-    pub(crate) texts: Vec<String>,
-    pub(crate) tags: Vec<String>,
+pub(crate) struct LineJsonStructure<'a> {
+    #[serde(rename = "texts")]
+    pub(crate) words: WordCount,
+    #[serde(borrow)]
+    pub(crate) tags: Vec<Tag<'a>>,
 }
 
-/// JsonStructure: Envuelve LineJsonStructure y le agrega el nombre del sitio.
-
+/// Tag: Etiqueta de una línea, prestada del JSON cuando es posible.
 #[derive(Debug, Deserialize)]
-pub(crate) struct JsonStructure {
-    pub(crate) site: String,
-    pub(crate) texts: Vec<String>,
-    pub(crate) tags: Vec<String>,
+pub(crate) struct Tag<'a>(#[serde(borrow)] pub(crate) Cow<'a, str>);
+
+/// WordCount: Cantidad total de palabras de los textos de una línea.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct WordCount(pub(crate) u32);
+
+impl<'de> Deserialize<'de> for WordCount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(TextsVisitor)
+    }
 }
 
-impl JsonStructure {
-    /// Crea una nueva instancia de `JsonStructure`.
-    ///
-    /// # Arguments
-    ///
-    /// * `site` - Nombre del sitio a asociar con la estructura JSON.
-    ///
-    /// # Returns
-    ///
-    /// Una nueva instancia de `JsonStructure` con el nombre del sitio especificado.
-    pub(crate) fn new(site: String) -> Self {
-        JsonStructure {
-            site,
-            texts: vec![],
-            tags: vec![],
+/// Visitor que recorre la lista de textos sumando sus palabras.
+struct TextsVisitor;
+
+impl<'de> Visitor<'de> for TextsVisitor {
+    type Value = WordCount;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("una lista de textos")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<WordCount, A::Error> {
+        let mut words = 0;
+        while let Some(TextWords(count)) = seq.next_element()? {
+            words += count;
         }
+        Ok(WordCount(words))
     }
+}
 
-    /// Carga la información de un `LineJsonStructure` en la estructura actual.
-    ///
-    /// # Arguments
-    ///
-    /// * `other` - Estructura `LineJsonStructure` que contiene la información a cargar.
-    pub(crate) fn load_info(&mut self, other: LineJsonStructure) {
-        // Sumar los valores de questions y words del otro TagData al actual
-        self.texts = other.texts;
-        self.tags = other.tags;
+/// TextWords: Cantidad de palabras de un texto, separadas por espacios en blanco.
+struct TextWords(u32);
+
+impl<'de> Deserialize<'de> for TextWords {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(TextVisitor)
+    }
+}
+
+/// Visitor que cuenta las palabras de un texto sin copiarlo.
+struct TextVisitor;
+
+impl Visitor<'_> for TextVisitor {
+    type Value = TextWords;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("un texto")
+    }
+
+    fn visit_str<E: de::Error>(self, text: &str) -> Result<TextWords, E> {
+        Ok(TextWords(text.split_whitespace().count() as u32))
     }
 }
 
 /// ResultData: Contiene la información TOTAL. Se utiliza para expresar el resultado final y pasarlo a json.
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub(crate) struct ResultData {
     pub(crate) padron: u32,
     pub(crate) sites: HashMap<String, SiteData>,
     pub(crate) tags: HashMap<String, TagData>,
     pub(crate) totals: TotalsData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) incomplete: Option<IncompleteData>,
+    /// Archivos procesados. Solo se escribe en el resultado de un shard (`--shard`), para que
+    /// `merge` pueda listar los archivos de los shards completos si el resultado es parcial.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) files: Vec<String>,
+    /// Shard del plan que generó el resultado. Solo se escribe en el resultado de un shard,
+    /// para que `merge` rechace shards repetidos o de otro plan.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) shard: Option<ShardId>,
 }
 
 impl ResultData {
@@ -87,6 +125,9 @@ impl ResultData {
                 chatty_sites: vec![],
                 chatty_tags: vec![],
             },
+            incomplete: None,
+            files: vec![],
+            shard: None,
         }
     }
 }
@@ -113,13 +154,16 @@ impl std::ops::Add for ResultData {
             sites: self.sites,
             tags: self.tags,
             totals: self.totals,
+            incomplete: self.incomplete,
+            files: self.files,
+            shard: self.shard,
         }
     }
 }
 
 /// SiteData: Contiene la información de un SITE
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
 pub(crate) struct SiteData {
     pub(crate) questions: u32,
     pub(crate) words: u32,
@@ -167,7 +211,7 @@ impl SiteData {
 }
 
 /// TagData: Contiene la información de un Tag
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub(crate) struct TagData {
     pub(crate) questions: u32,
     pub(crate) words: u32,
@@ -199,12 +243,213 @@ impl TagData {
 }
 
 /// TotalsData: Contiene la información total de los totales.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub(crate) struct TotalsData {
     pub(crate) chatty_sites: Vec<String>,
     pub(crate) chatty_tags: Vec<String>,
 }
 
+/// Accumulator: Resultado parcial de un worker, con los sitios y etiquetas indexados por los
+/// identificadores del interner.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Accumulator {
+    pub(crate) sites: HashMap<Id, SiteCounters>,
+}
+
+/// SiteCounters: Contadores de un sitio, con los de cada etiqueta indexados por su
+/// identificador.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct SiteCounters {
+    pub(crate) questions: u32,
+    pub(crate) words: u32,
+    pub(crate) tags: HashMap<Id, TagData>,
+}
+
+impl SiteCounters {
+    /// Devuelve los contadores de una etiqueta, creándolos si hace falta.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - Identificador de la etiqueta.
+    pub(crate) fn tag_mut(&mut self, tag: Id) -> &mut TagData {
+        self.tags.entry(tag).or_insert_with(|| TagData::new(0, 0))
+    }
+
+    /// Combina los contadores de otro worker para el mismo sitio.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Contadores a sumar.
+    fn combine(&mut self, mut other: Self) {
+        self.questions += other.questions;
+        self.words += other.words;
+        if other.tags.len() > self.tags.len() {
+            std::mem::swap(&mut self.tags, &mut other.tags);
+        }
+        for (tag, other_data) in other.tags {
+            let tag_data = self.tag_mut(tag);
+            tag_data.questions += other_data.questions;
+            tag_data.words += other_data.words;
+        }
+    }
+}
+
+impl std::ops::Add for Accumulator {
+    type Output = Self;
+
+    fn add(mut self, mut other: Self) -> Self {
+        if other.sites.len() > self.sites.len() {
+            std::mem::swap(&mut self, &mut other);
+        }
+        for (site, counters) in other.sites {
+            match self.sites.entry(site) {
+                Entry::Occupied(mut entry) => entry.get_mut().combine(counters),
+                Entry::Vacant(entry) => {
+                    entry.insert(counters);
+                }
+            }
+        }
+        self
+    }
+}
+
+impl Accumulator {
+    /// Genera el `ResultData` equivalente, resolviendo los nombres de sitios y etiquetas.
+    ///
+    /// # Arguments
+    ///
+    /// * `padron` - Número de padrón del `ResultData` a generar.
+    pub(crate) fn into_result_data(self, padron: u32) -> ResultData {
+        let names = names();
+        let mut sites: HashMap<String, SiteData> = HashMap::new();
+        let mut tags: HashMap<String, TagData> = HashMap::new();
+        for (site, counters) in self.sites {
+            let mut site_tags: HashMap<String, TagData> = HashMap::new();
+            for (tag, tag_data) in counters.tags {
+                let name = names.get(tag);
+                let total = tags
+                    .entry(name.to_string())
+                    .or_insert_with(|| TagData::new(0, 0));
+                total.questions += tag_data.questions;
+                total.words += tag_data.words;
+                site_tags.insert(name.to_string(), tag_data);
+            }
+            sites.insert(
+                names.get(site).to_string(),
+                SiteData::new(counters.questions, counters.words, site_tags),
+            );
+        }
+        ResultData::new(padron, sites, tags)
+    }
+
+    /// Genera el `Accumulator` equivalente a un `ResultData`, internando sus nombres. Los
+    /// totals del `ResultData` se descartan.
+    ///
+    /// # Arguments
+    ///
+    /// * `result_data` - Resultado a convertir, por ejemplo leído de un checkpoint.
+    pub(crate) fn from_result_data(result_data: ResultData) -> Self {
+        let sites = result_data
+            .sites
+            .into_iter()
+            .map(|(site, site_data)| {
+                let counters = SiteCounters {
+                    questions: site_data.questions,
+                    words: site_data.words,
+                    tags: site_data
+                        .tags
+                        .into_iter()
+                        .map(|(tag, tag_data)| (intern(&tag), tag_data))
+                        .collect(),
+                };
+                (intern(&site), counters)
+            })
+            .collect();
+        Accumulator { sites }
+    }
+}
+
+/// FileStatus: Indica hasta dónde se procesó un archivo.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FileStatus {
+    Complete,
+    Partial,
+    NotProcessed,
+}
+
+/// ByteRange: Rango de bytes `[start, end)` de un archivo, alineado a saltos de línea.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ByteRange {
+    pub(crate) start: u64,
+    pub(crate) end: u64,
+}
+
+/// FileSource: Indica de dónde se obtuvo el resultado de un archivo.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FileSource {
+    Processed,
+    Checkpoint,
+    Cache,
+}
+
+/// FileStats: Contiene las métricas del procesamiento de un archivo.
+///
+/// Los tiempos de parseo y agregación son la suma de lo que tardó cada worker, por lo que
+/// pueden superar el tiempo transcurrido del archivo.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub(crate) struct FileStats {
+    pub(crate) bytes: u64,
+    pub(crate) lines: u64,
+    pub(crate) parse_ns: u64,
+    pub(crate) aggregation_ns: u64,
+    pub(crate) elapsed_ns: u64,
+}
+
+impl std::ops::Add for FileStats {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        FileStats {
+            bytes: self.bytes + other.bytes,
+            lines: self.lines + other.lines,
+            parse_ns: self.parse_ns + other.parse_ns,
+            aggregation_ns: self.aggregation_ns + other.aggregation_ns,
+            elapsed_ns: self.elapsed_ns.max(other.elapsed_ns),
+        }
+    }
+}
+
+/// FileResult: Contiene el resultado parcial de un archivo junto con su estado. `data` usa los
+/// identificadores del interner, por lo que solo es válido dentro de la `Session` en la que se
+/// generó.
+#[derive(Debug)]
+pub(crate) struct FileResult {
+    pub(crate) path: PathBuf,
+    pub(crate) status: FileStatus,
+    pub(crate) source: FileSource,
+    pub(crate) stats: FileStats,
+    pub(crate) data: Accumulator,
+}
+
+/// IncompleteData: Marca un resultado como parcial e indica el estado de cada archivo.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub(crate) struct IncompleteData {
+    pub(crate) complete_files: Vec<String>,
+    pub(crate) partial_files: Vec<String>,
+    pub(crate) not_processed_files: Vec<String>,
+}
+
+/// ShardId: Identifica el shard de un plan que generó un resultado.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub(crate) struct ShardId {
+    /// Hash SHA-256 del manifest del plan.
+    pub(crate) manifest: String,
+    pub(crate) index: usize,
+    pub(crate) count: usize,
+}
+
 /// Trait que permite la reducción de las estructuras `SiteData` y `TagData`.
 trait Reducible {
     /// Combina dos instancias de la estructura.
@@ -1,7 +1,13 @@
 #[cfg(test)]
 mod tests {
 
+    use crate::config::Config;
+    use crate::intern::{intern, names, Session, SharedInterner};
+    use crate::pools::Pools;
     use crate::processors::*;
+    use crate::structs::{LineJsonStructure, ResultData, SiteData, Tag, TagData};
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
 
     /*  site1
     {"texts": ["1", "2"], "tags": ["1", "tag repetido"]}
@@ -12,6 +18,73 @@ mod tests {
     {"texts": ["10", "11 12 13 14"], "tags": ["4", "tag repetido"]}
     */
 
+    fn pools() -> Pools {
+        Pools::from_config(&Config::default()).unwrap()
+    }
+
+    /// TempDir: directorio temporal de un test, que se borra al salir de scope aunque el
+    /// test falle.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        /// Crea un directorio temporal vacío, único por test y por proceso.
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("tp1-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+
+        /// Escribe un archivo dentro del directorio y devuelve su ruta.
+        fn write(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+            let path = self.0.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path
+        }
+
+        /// Devuelve los archivos del directorio, igual que `list_dir`.
+        fn files(&self) -> Vec<PathBuf> {
+            list_dir(&self.0)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Devuelve una configuración por cada parser disponible, para correr las mismas
+    /// verificaciones con todos. `simd` solo está disponible compilando con la feature.
+    fn parser_configs() -> Vec<Config> {
+        use crate::config::Parser;
+
+        let parsers: &[Parser] = if cfg!(feature = "simd") {
+            &[Parser::Serde, Parser::Simd]
+        } else {
+            &[Parser::Serde]
+        };
+        parsers
+            .iter()
+            .map(|&parser| Config {
+                parser,
+                ..Config::default()
+            })
+            .collect()
+    }
+
+    /// Procesa los archivos de `/test1` con cada parser.
+    fn test1_results(pools: &Pools) -> Vec<ResultData> {
+        parser_configs()
+            .iter()
+            .map(|config| process_files(&list_files("/test1"), config, pools, None))
+            .collect()
+    }
+
     fn assert_same_elements(expected: &[String], actual: &[String]) {
         for elem in expected {
             assert!(actual.contains(elem), "Missing element: {:?}", elem);
@@ -24,136 +97,149 @@ mod tests {
 
     #[test]
     fn site_words_count_test() {
-        let result_data = process_files(&list_files("/test1"));
-        let words_count_site1: u32 = 7;
-        let words_count_site2: u32 = 7;
-        assert_eq!(
-            result_data.sites.get("site1").unwrap().words,
-            words_count_site1
-        );
-        assert_eq!(
-            result_data.sites.get("site2").unwrap().words,
-            words_count_site2
-        );
+        let pools = pools();
+        for result_data in test1_results(&pools) {
+            let words_count_site1: u32 = 7;
+            let words_count_site2: u32 = 7;
+            assert_eq!(
+                result_data.sites.get("site1").unwrap().words,
+                words_count_site1
+            );
+            assert_eq!(
+                result_data.sites.get("site2").unwrap().words,
+                words_count_site2
+            );
+        }
     }
 
     #[test]
     fn site_questions_count_test() {
-        let result_data = process_files(&list_files("/test1"));
-        let questions_count_site1: u32 = 2;
-        let questions_count_site2: u32 = 2;
-        assert_eq!(
-            result_data.sites.get("site1").unwrap().questions,
-            questions_count_site1
-        );
-        assert_eq!(
-            result_data.sites.get("site2").unwrap().questions,
-            questions_count_site2
-        );
+        let pools = pools();
+        for result_data in test1_results(&pools) {
+            let questions_count_site1: u32 = 2;
+            let questions_count_site2: u32 = 2;
+            assert_eq!(
+                result_data.sites.get("site1").unwrap().questions,
+                questions_count_site1
+            );
+            assert_eq!(
+                result_data.sites.get("site2").unwrap().questions,
+                questions_count_site2
+            );
+        }
     }
 
     #[test]
     fn tag_site_words_count_test() {
-        let result_data = process_files(&list_files("/test1"));
-
-        let expected_data = vec![
-            ("site1", "1", 2),
-            ("site1", "2", 5),
-            ("site1", "tag repetido", 7),
-            ("site2", "3", 2),
-            ("site2", "4", 5),
-            ("site2", "tag repetido", 7),
-        ];
+        let pools = pools();
+        for result_data in test1_results(&pools) {
+            let expected_data = vec![
+                ("site1", "1", 2),
+                ("site1", "2", 5),
+                ("site1", "tag repetido", 7),
+                ("site2", "3", 2),
+                ("site2", "4", 5),
+                ("site2", "tag repetido", 7),
+            ];
 
-        for (site, tag, expected_words) in expected_data {
-            let actual_words = result_data
-                .sites
-                .get(site)
-                .and_then(|site_data| site_data.tags.get(tag).map(|tag_data| tag_data.words))
-                .unwrap_or(0);
+            for (site, tag, expected_words) in expected_data {
+                let actual_words = result_data
+                    .sites
+                    .get(site)
+                    .and_then(|site_data| site_data.tags.get(tag).map(|tag_data| tag_data.words))
+                    .unwrap_or(0);
 
-            assert_eq!(actual_words, expected_words, "Site: {}, Tag: {}", site, tag);
+                assert_eq!(actual_words, expected_words, "Site: {}, Tag: {}", site, tag);
+            }
         }
     }
 
     #[test]
     fn tag_site_questions_count_test() {
-        let result_data = process_files(&list_files("/test1"));
-
-        let expected_data = vec![
-            ("site1", "1", 1),
-            ("site1", "2", 1),
-            ("site1", "tag repetido", 2),
-            ("site2", "3", 1),
-            ("site2", "4", 1),
-            ("site2", "tag repetido", 2),
-        ];
+        let pools = pools();
+        for result_data in test1_results(&pools) {
+            let expected_data = vec![
+                ("site1", "1", 1),
+                ("site1", "2", 1),
+                ("site1", "tag repetido", 2),
+                ("site2", "3", 1),
+                ("site2", "4", 1),
+                ("site2", "tag repetido", 2),
+            ];
 
-        for (site, tag, expected_words) in expected_data {
-            let actual_words = result_data
-                .sites
-                .get(site)
-                .and_then(|site_data| site_data.tags.get(tag).map(|tag_data| tag_data.questions))
-                .unwrap_or(0);
+            for (site, tag, expected_words) in expected_data {
+                let actual_words = result_data
+                    .sites
+                    .get(site)
+                    .and_then(|site_data| {
+                        site_data.tags.get(tag).map(|tag_data| tag_data.questions)
+                    })
+                    .unwrap_or(0);
 
-            assert_eq!(actual_words, expected_words, "Site: {}, Tag: {}", site, tag);
+                assert_eq!(actual_words, expected_words, "Site: {}, Tag: {}", site, tag);
+            }
         }
     }
 
     #[test]
     fn tag_total_questions_count_test() {
-        let result_data = process_files(&list_files("/test1"));
+        let pools = pools();
+        for result_data in test1_results(&pools) {
+            let expected_data = vec![("1", 1), ("2", 1), ("3", 1), ("4", 1), ("tag repetido", 4)];
 
-        let expected_data = vec![("1", 1), ("2", 1), ("3", 1), ("4", 1), ("tag repetido", 4)];
+            for (tag, expected_questions) in expected_data {
+                let actual_questions = result_data
+                    .tags
+                    .get(tag)
+                    .map(|tag_data| tag_data.questions)
+                    .unwrap_or(0);
 
-        for (tag, expected_questions) in expected_data {
-            let actual_questions = result_data
-                .tags
-                .get(tag)
-                .map(|tag_data| tag_data.questions)
-                .unwrap_or(0);
-
-            assert_eq!(actual_questions, expected_questions, "Tag: {}", tag);
+                assert_eq!(actual_questions, expected_questions, "Tag: {}", tag);
+            }
         }
     }
 
     #[test]
     fn tag_total_words_count_test() {
-        let result_data = process_files(&list_files("/test1"));
-
-        let expected_data = vec![("1", 2), ("2", 5), ("3", 2), ("4", 5), ("tag repetido", 14)];
+        let pools = pools();
+        for result_data in test1_results(&pools) {
+            let expected_data = vec![("1", 2), ("2", 5), ("3", 2), ("4", 5), ("tag repetido", 14)];
 
-        for (tag, expected_words) in expected_data {
-            let actual_words = result_data
-                .tags
-                .get(tag)
-                .map(|tag_data| tag_data.words)
-                .unwrap_or(0);
+            for (tag, expected_words) in expected_data {
+                let actual_words = result_data
+                    .tags
+                    .get(tag)
+                    .map(|tag_data| tag_data.words)
+                    .unwrap_or(0);
 
-            assert_eq!(actual_words, expected_words, "Tag: {}", tag);
+                assert_eq!(actual_words, expected_words, "Tag: {}", tag);
+            }
         }
     }
 
     #[test]
     fn site_chatty_tags_test() {
+        let pools = pools();
         let expected_site1: Vec<String> =
             vec!["1".to_string(), "2".to_string(), "tag repetido".to_string()];
         let expected_site2: Vec<String> =
             vec!["3".to_string(), "4".to_string(), "tag repetido".to_string()];
-        let mut result_data = process_files(&list_files("/test1"));
-        process_totals(&mut result_data);
-        assert_same_elements(
-            &expected_site1,
-            &result_data.sites.get("site1").unwrap().chatty_tags,
-        );
-        assert_same_elements(
-            &expected_site2,
-            &result_data.sites.get("site2").unwrap().chatty_tags,
-        );
+        for mut result_data in test1_results(&pools) {
+            process_totals(&mut result_data);
+            assert_same_elements(
+                &expected_site1,
+                &result_data.sites.get("site1").unwrap().chatty_tags,
+            );
+            assert_same_elements(
+                &expected_site2,
+                &result_data.sites.get("site2").unwrap().chatty_tags,
+            );
+        }
     }
 
     #[test]
     fn total_chatty_tags_test() {
+        let pools = pools();
         let expected: Vec<String> = vec![
             "1".to_string(),
             "2".to_string(),
@@ -161,16 +247,1273 @@ mod tests {
             "4".to_string(),
             "tag repetido".to_string(),
         ];
-        let mut result_data = process_files(&list_files("/test1"));
-        process_totals(&mut result_data);
-        assert_same_elements(&expected, &result_data.totals.chatty_tags);
+        for mut result_data in test1_results(&pools) {
+            process_totals(&mut result_data);
+            assert_same_elements(&expected, &result_data.totals.chatty_tags);
+        }
     }
 
     #[test]
     fn total_chatty_sites_test() {
+        let pools = pools();
         let expected: Vec<String> = vec!["site1".to_string(), "site2".to_string()];
-        let mut result_data = process_files(&list_files("/test1"));
-        process_totals(&mut result_data);
-        assert_same_elements(&expected, &result_data.totals.chatty_sites);
+        for mut result_data in test1_results(&pools) {
+            process_totals(&mut result_data);
+            assert_same_elements(&expected, &result_data.totals.chatty_sites);
+        }
+    }
+
+    #[test]
+    fn interner_test() {
+        let _session = Session::start();
+        let a = intern("interner-a");
+        let b = intern("interner-b");
+
+        assert_ne!(a, b);
+        assert_eq!(a, intern("interner-a"));
+        assert_eq!(
+            a,
+            std::thread::spawn(|| intern("interner-a")).join().unwrap()
+        );
+        let names = names();
+        assert_eq!(names.get(a), "interner-a");
+        assert_eq!(names.get(b), "interner-b");
+    }
+
+    #[test]
+    fn interner_clears_after_last_session_test() {
+        let interner = SharedInterner::default();
+        interner.open();
+        interner.open();
+        interner.intern("a");
+        interner.intern("b");
+        interner.close();
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.generation(), 0);
+
+        interner.close();
+        assert_eq!(interner.len(), 0);
+        assert_eq!(interner.generation(), 1);
+
+        interner.open();
+        assert_eq!(interner.intern("b").1, 0);
+        interner.close();
+    }
+
+    #[test]
+    fn resume_from_checkpoints_test() {
+        let pools = pools();
+        let state_dir = TempDir::new("state");
+        let files = list_files("/test1");
+        let config = Config {
+            state_dir: Some(state_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let expected = process_files(&files, &config, &pools, None);
+        assert_eq!(state_dir.files().len(), files.len());
+
+        let resume_config = Config {
+            resume: true,
+            ..config
+        };
+        let resumed = process_files(&files, &resume_config, &pools, None);
+
+        assert_eq!(expected, resumed);
+        assert_eq!(
+            expected,
+            process_files(&files, &Config::default(), &pools, None)
+        );
+    }
+
+    #[test]
+    fn resume_detects_same_size_and_mtime_test() {
+        let pools = pools();
+        let state_dir = TempDir::new("state-rewrite");
+        let dir = TempDir::new("state-rewrite-in");
+        let files = [dir.write("site1.jsonl", "{\"texts\": [\"a b\"], \"tags\": [\"x\"]}\n")];
+        let path = &files[0];
+        let config = Config {
+            state_dir: Some(state_dir.path().to_path_buf()),
+            resume: true,
+            ..Config::default()
+        };
+        process_files(&files, &config, &pools, None);
+
+        // se reescribe con el mismo tamaño y se restaura la fecha de modificación
+        let mtime = std::fs::metadata(path).unwrap().modified().unwrap();
+        std::fs::write(path, "{\"texts\": [\"c d\"], \"tags\": [\"y\"]}\n").unwrap();
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(mtime).unwrap();
+        drop(file);
+
+        let resumed = process_files(&files, &config, &pools, None);
+        assert_eq!(
+            resumed,
+            process_files(&files, &Config::default(), &pools, None)
+        );
+        assert!(resumed.tags.contains_key("y"));
+        assert!(!resumed.tags.contains_key("x"));
+    }
+
+    #[test]
+    fn checkpoints_keyed_by_full_path_test() {
+        let pools = pools();
+        let state_dir = TempDir::new("state-paths");
+        let first = TempDir::new("state-paths-a");
+        let second = TempDir::new("state-paths-b");
+        first.write("site1.jsonl", "{\"texts\": [\"a\"], \"tags\": [\"x\"]}\n");
+        second.write("site1.jsonl", "{\"texts\": [\"b c\"], \"tags\": [\"y\"]}\n");
+        let mut files = first.files();
+        files.extend(second.files());
+        let config = Config {
+            state_dir: Some(state_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let expected = process_files(&files, &config, &pools, None);
+        assert_eq!(state_dir.files().len(), 2);
+
+        let resume_config = Config {
+            resume: true,
+            ..config
+        };
+        assert_eq!(
+            expected,
+            process_files(&files, &resume_config, &pools, None)
+        );
+        assert_eq!(expected.sites["site1"].words, 3);
+    }
+
+    #[test]
+    fn cached_partials_test() {
+        let pools = pools();
+        let _session = Session::start();
+        let cache_dir = TempDir::new("cache");
+        let files = list_files("/test1");
+        let config = Config {
+            cache_dir: Some(cache_dir.path().to_path_buf()),
+            ..Config::default()
+        };
+        let expected = process_files(&files, &config, &pools, None);
+        assert_eq!(cache_dir.files().len(), files.len());
+
+        // el cache es por contenido: una copia en otra ruta también se sirve desde el cache
+        let copies = TempDir::new("cache-copies");
+        for path in &files {
+            std::fs::copy(path, copies.path().join(path.file_name().unwrap())).unwrap();
+        }
+        let cached: Vec<_> = copies
+            .files()
+            .iter()
+            .map(|path| process_file_with_state(path, &config, &pools, None, None))
+            .collect();
+        assert!(cached
+            .iter()
+            .all(|result| result.source == crate::structs::FileSource::Cache));
+        assert_eq!(expected, merge_results(cached));
+    }
+
+    #[test]
+    fn split_ranges_test() {
+        for path in list_files("/test1") {
+            let contents = std::fs::read(&path).unwrap();
+            let ranges = split_ranges(&path, 8).unwrap();
+
+            assert_eq!(ranges.first().unwrap().start, 0);
+            assert_eq!(ranges.last().unwrap().end, contents.len() as u64);
+            for window in ranges.windows(2) {
+                assert_eq!(window[0].end, window[1].start);
+                assert_eq!(contents[window[1].start as usize - 1], b'\n');
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_files_test() {
+        let pools = pools();
+        let files = list_files("/test1");
+        let config = Config {
+            chunk_size: Some(8),
+            no_mmap: true,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            process_files(&files, &config, &pools, None),
+            process_files(&files, &Config::default(), &pools, None)
+        );
+    }
+
+    #[test]
+    fn tree_merge_test() {
+        let pools = pools();
+        let dir = TempDir::new("merge");
+        // 20 archivos distintos, con etiquetas compartidas entre sitios
+        for site in 0..20 {
+            let lines: Vec<String> = (0..site % 5 + 1)
+                .map(|line| {
+                    format!(
+                        "{{\"texts\": [\"{}\"], \"tags\": [\"t{}\", \"s{}\"]}}",
+                        "w ".repeat(site + line),
+                        (site + line) % 4,
+                        site
+                    )
+                })
+                .collect();
+            dir.write(&format!("site{}.jsonl", site), lines.join("\n"));
+        }
+        let files = dir.files();
+        assert_eq!(files.len(), 20);
+
+        let sequential = files.iter().fold(
+            ResultData::new(PADRON, HashMap::new(), HashMap::new()),
+            |acc, path| {
+                acc + process_files(std::slice::from_ref(path), &Config::default(), &pools, None)
+            },
+        );
+        let merged = process_files(&files, &Config::default(), &pools, None);
+
+        assert_eq!(sequential, merged);
+        assert_eq!(merged.sites.len(), 20);
+    }
+
+    #[test]
+    fn backends_test() {
+        use crate::config::Backend;
+
+        let pools = pools();
+        let dir = TempDir::new("backends");
+        let lines: Vec<String> = (0..3000)
+            .map(|i| {
+                format!(
+                    "{{\"texts\": [\"a b {}\"], \"tags\": [\"t{}\"]}}",
+                    i,
+                    i % 17
+                )
+            })
+            .collect();
+        dir.write("site5.jsonl", lines.join("\n"));
+        dir.write("site6.jsonl", "");
+        let mut files = list_files("/test1");
+        files.extend(dir.files());
+        let expected = process_files(&files, &Config::default(), &pools, None);
+
+        for backend in [
+            Backend::Pipeline,
+            Backend::Threads,
+            Backend::Actors,
+            Backend::Sharded,
+            Backend::Async,
+        ] {
+            let config = Config {
+                backend,
+                chunk_size: Some(1000),
+                ..Config::default()
+            };
+            assert_eq!(expected, process_files(&files, &config, &pools, None));
+        }
+        let buffered_threads = Config {
+            backend: Backend::Threads,
+            chunk_size: Some(1000),
+            no_mmap: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            expected,
+            process_files(&files, &buffered_threads, &pools, None)
+        );
+    }
+
+    #[test]
+    fn backends_open_error_test() {
+        use crate::backends::{actors, async_io, pipeline, sharded, threads};
+        use std::time::Instant;
+
+        let pools = pools();
+        let _session = Session::start();
+        let dir = TempDir::new("backends-error");
+        let missing = dir.path().join("missing.jsonl");
+        let mut files = list_files("/test1");
+        files.push(missing.clone());
+        let config = Config::default();
+
+        let mut errors = pools.parse().install(|| {
+            vec![
+                pipeline::process(&files, &config, Instant::now()).err(),
+                threads::process(&files, &config, Instant::now()).err(),
+                actors::process(&files, &config, Instant::now()).err(),
+                sharded::process(&files, &config, Instant::now()).err(),
+            ]
+        });
+        // el backend async parsea en el pool que recibe, por lo que no puede correr dentro de él
+        errors.push(async_io::process(&files, &config, pools.parse(), Instant::now()).err());
+        for error in errors {
+            let error = error.expect("el backend no informó el error");
+            assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+            assert!(error.to_string().contains(&missing.display().to_string()));
+        }
+    }
+
+    #[test]
+    fn distributed_workers_test() {
+        use crate::distributed::{coordinate, serve, Timeouts};
+        use std::net::{TcpListener, TcpStream};
+
+        let pools = pools();
+        let _session = Session::start();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (processed, (results, retries)) = std::thread::scope(|scope| {
+            let workers: Vec<_> = (0..2)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let stream = TcpStream::connect(address).unwrap();
+                        serve(stream, &Config::default(), &pools).unwrap()
+                    })
+                })
+                .collect();
+            let coordinated = coordinate(&listener, &files, Timeouts::default()).unwrap();
+            let processed: usize = workers.into_iter().map(|w| w.join().unwrap()).sum();
+            (processed, coordinated)
+        });
+        assert_eq!(processed, files.len());
+        assert_eq!(retries.reassigned(), 0);
+        assert_eq!(expected, merge_results(results));
+    }
+
+    #[test]
+    fn distributed_silent_worker_test() {
+        use crate::distributed::{coordinate, read_frame, serve, Timeouts};
+        use std::net::{TcpListener, TcpStream};
+        use std::time::Duration;
+
+        let pools = pools();
+        let _session = Session::start();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let timeouts = Timeouts {
+            worker: Duration::from_millis(300),
+            task: Duration::from_secs(60),
+        };
+        let (processed, (results, retries)) = std::thread::scope(|scope| {
+            // el primer worker recibe un archivo y no responde nunca
+            let silent = scope.spawn(|| {
+                let mut stream = TcpStream::connect(address).unwrap();
+                let _: crate::distributed::ToWorker = read_frame(&mut stream).unwrap();
+                let worker = scope.spawn(|| {
+                    let stream = TcpStream::connect(address).unwrap();
+                    serve(stream, &Config::default(), &pools).unwrap()
+                });
+                let processed = worker.join().unwrap();
+                drop(stream);
+                processed
+            });
+            let coordinated = coordinate(&listener, &files, timeouts).unwrap();
+            (silent.join().unwrap(), coordinated)
+        });
+        assert_eq!(processed, files.len());
+        assert_eq!(retries.timed_out, 1);
+        assert_eq!(retries.duplicates, 0);
+        assert_eq!(expected, merge_results(results));
+    }
+
+    #[test]
+    fn distributed_incomplete_result_test() {
+        use crate::distributed::{
+            coordinate, read_frame, write_frame, RemoteResult, Timeouts, ToCoordinator, ToWorker,
+            MAX_ATTEMPTS,
+        };
+        use crate::structs::{FileResult, FileStatus};
+        use std::net::{TcpListener, TcpStream};
+
+        let pools = pools();
+        let _session = Session::start();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        // worker que devuelve resultados parciales vacíos en los primeros `incomplete` archivos
+        let worker = |address, incomplete: usize| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            let mut assigned = 0;
+            while let ToWorker::Assign { index, path } = read_frame(&mut stream).unwrap() {
+                let result = if assigned < incomplete {
+                    FileResult {
+                        status: FileStatus::Partial,
+                        ..not_processed(&path)
+                    }
+                } else {
+                    process_file_with_state(&path, &Config::default(), &pools, None, None)
+                };
+                assigned += 1;
+                let result = RemoteResult::from(result);
+                write_frame(&mut stream, &ToCoordinator::Done { index, result }).unwrap();
+            }
+        };
+
+        // el archivo con resultado parcial se reasigna y cuenta solo el resultado completo
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (results, retries) = std::thread::scope(|scope| {
+            scope.spawn(|| worker(address, 1));
+            coordinate(&listener, &files, Timeouts::default()).unwrap()
+        });
+        assert_eq!(retries.incomplete, 1);
+        assert_eq!(retries.duplicates, 0);
+        assert!(results.iter().all(|r| r.status == FileStatus::Complete));
+        assert_eq!(expected, merge_results(results));
+
+        // al llegar al límite de intentos se acepta el último resultado incompleto
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (results, retries) = std::thread::scope(|scope| {
+            scope.spawn(|| worker(address, usize::MAX));
+            coordinate(&listener, &files, Timeouts::default()).unwrap()
+        });
+        assert_eq!(retries.incomplete, files.len() * (MAX_ATTEMPTS - 1));
+        assert!(results.iter().all(|r| r.status == FileStatus::Partial));
+    }
+
+    #[test]
+    fn distributed_lost_worker_test() {
+        use crate::distributed::{coordinate, read_frame, serve, Timeouts, ToWorker};
+        use std::net::{TcpListener, TcpStream};
+
+        let pools = pools();
+        let _session = Session::start();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (processed, (results, retries)) = std::thread::scope(|scope| {
+            // el primer worker recibe un archivo y cierra la conexión
+            let lost = scope.spawn(|| {
+                let mut stream = TcpStream::connect(address).unwrap();
+                let _: ToWorker = read_frame(&mut stream).unwrap();
+                drop(stream);
+                let stream = TcpStream::connect(address).unwrap();
+                serve(stream, &Config::default(), &pools).unwrap()
+            });
+            let coordinated = coordinate(&listener, &files, Timeouts::default()).unwrap();
+            (lost.join().unwrap(), coordinated)
+        });
+        assert_eq!(processed, files.len());
+        assert_eq!(retries.lost, 1);
+        assert_eq!(retries.reassigned(), 1);
+        assert_eq!(expected, merge_results(results));
+    }
+
+    #[test]
+    fn distributed_speculative_duplicate_test() {
+        use crate::distributed::{
+            coordinate, read_frame, serve, write_frame, RemoteResult, Timeouts, ToCoordinator,
+            ToWorker,
+        };
+        use std::net::{TcpListener, TcpStream};
+        use std::time::Duration;
+
+        let pools = pools();
+        let _session = Session::start();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let timeouts = Timeouts {
+            worker: Duration::from_secs(10),
+            task: Duration::from_millis(200),
+        };
+        let (processed, (results, retries)) = std::thread::scope(|scope| {
+            // el primer worker responde recién cuando el otro ya procesó todos los archivos
+            let slow = scope.spawn(|| {
+                let mut stream = TcpStream::connect(address).unwrap();
+                let ToWorker::Assign { index, path } = read_frame(&mut stream).unwrap() else {
+                    panic!("se esperaba un archivo asignado");
+                };
+                let worker = scope.spawn(|| {
+                    let stream = TcpStream::connect(address).unwrap();
+                    serve(stream, &Config::default(), &pools).unwrap()
+                });
+                let processed = worker.join().unwrap();
+                let result = RemoteResult::from(process_file_with_state(
+                    &path,
+                    &Config::default(),
+                    &pools,
+                    None,
+                    None,
+                ));
+                write_frame(&mut stream, &ToCoordinator::Done { index, result }).unwrap();
+                assert!(matches!(read_frame(&mut stream).unwrap(), ToWorker::Finish));
+                processed
+            });
+            let coordinated = coordinate(&listener, &files, timeouts).unwrap();
+            (slow.join().unwrap(), coordinated)
+        });
+        assert_eq!(processed, files.len());
+        assert_eq!(retries.slow, 1);
+        assert_eq!(retries.duplicates, 1);
+        assert_eq!(expected, merge_results(results));
+    }
+
+    #[test]
+    fn child_process_protocol_test() {
+        use crate::distributed::{read_frame, write_frame, RemoteResult};
+        use crate::processes::serve_child;
+
+        let pools = pools();
+        let _session = Session::start();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        let sizes: Vec<u64> = files.iter().map(|f| f.metadata().unwrap().len()).collect();
+        let shards = balance(&sizes, 3);
+        assert_eq!(shards.len(), 3);
+        let mut indices: Vec<usize> = shards.concat();
+        indices.sort();
+        assert_eq!(indices, (0..files.len()).collect::<Vec<_>>());
+
+        let mut input = Vec::new();
+        write_frame(&mut input, &files).unwrap();
+        let mut output = Vec::new();
+        serve_child(input.as_slice(), &mut output, &Config::default(), &pools).unwrap();
+        let mut output = output.as_slice();
+        let mut results: Vec<_> = (0..files.len())
+            .map(|_| {
+                let (position, result): (usize, RemoteResult) = read_frame(&mut output).unwrap();
+                result.into_file_result(&files[position])
+            })
+            .collect();
+        assert!(output.is_empty());
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(expected, merge_results(results));
+    }
+
+    #[test]
+    fn plan_shards_and_merge_test() {
+        use crate::output::write_result;
+        use crate::plan::{build_manifest, merge_parts, process_shard};
+
+        let pools = pools();
+        let dir = TempDir::new("plan");
+        let files = list_files("/test1");
+        let mut expected = process_files(&files, &Config::default(), &pools, None);
+        process_totals(&mut expected);
+
+        // con chunks de 40 bytes cada archivo se divide en rangos
+        let manifest = build_manifest(&files, 3, 40).unwrap();
+        assert_eq!(manifest.shards.len(), 3);
+        assert!(manifest.shards.iter().all(|shard| !shard.parts.is_empty()));
+        assert!(manifest
+            .shards
+            .iter()
+            .flat_map(|shard| &shard.parts)
+            .all(|part| part.range.is_some()));
+        let manifest_path = dir.write("manifest.json", serde_json::to_string(&manifest).unwrap());
+
+        let parts: Vec<_> = (0..3)
+            .map(|index| {
+                let config = Config {
+                    manifest: Some(manifest_path.clone()),
+                    shard: Some((index, 3)),
+                    ..Config::default()
+                };
+                let mut result_data = process_shard(&config, &pools);
+                process_totals(&mut result_data);
+                let part = dir.path().join(format!("part-{}.json", index));
+                write_result(&result_data, Some(&part)).unwrap();
+                part
+            })
+            .collect();
+        assert_eq!(expected, merge_parts(&parts).unwrap());
+    }
+
+    #[test]
+    fn merge_partial_shard_test() {
+        use crate::output::write_result;
+        use crate::plan::{build_manifest, merge_parts, process_shard};
+        use crate::structs::IncompleteData;
+
+        let pools = pools();
+        let dir = TempDir::new("plan_partial");
+        let files = list_files("/test1");
+
+        // sin dividir en rangos, cada archivo queda en su propio shard, con su ruta absoluta
+        let manifest = build_manifest(&files, 2, 1 << 30).unwrap();
+        assert!(manifest
+            .shards
+            .iter()
+            .flat_map(|shard| &shard.parts)
+            .all(|part| part.path.is_absolute()));
+        let manifest_path = dir.write("manifest.json", serde_json::to_string(&manifest).unwrap());
+
+        let shards: Vec<_> = (0..2)
+            .map(|index| {
+                let config = Config {
+                    manifest: Some(manifest_path.clone()),
+                    shard: Some((index, 2)),
+                    ..Config::default()
+                };
+                process_shard(&config, &pools)
+            })
+            .collect();
+        assert!(shards.iter().all(|shard| shard.files.len() == 1));
+        let complete_files = shards[0].files.clone();
+        let partial_files = shards[1].files.clone();
+
+        let mut partial = shards[1].clone();
+        partial.incomplete = Some(IncompleteData {
+            complete_files: vec![],
+            partial_files: partial_files.clone(),
+            not_processed_files: vec![],
+        });
+        let parts = [
+            dir.write("part-0.json", serde_json::to_string(&shards[0]).unwrap()),
+            dir.path().join("part-1.json"),
+        ];
+        write_result(&partial, Some(&parts[1])).unwrap();
+
+        // los archivos del shard completo siguen listados como completos
+        let merged = merge_parts(&parts).unwrap();
+        assert!(merged.files.is_empty());
+        assert_eq!(
+            merged.incomplete,
+            Some(IncompleteData {
+                complete_files,
+                partial_files,
+                not_processed_files: vec![],
+            })
+        );
+
+        write_result(&shards[1], Some(&parts[1])).unwrap();
+        assert_eq!(merge_parts(&parts).unwrap().incomplete, None);
+
+        // un shard repetido o de otro plan no se combina
+        let repeated = [parts[0].clone(), parts[0].clone()];
+        assert!(merge_parts(&repeated).is_err());
+        let mut other = shards[1].clone();
+        other.shard.as_mut().unwrap().manifest = "otro".to_string();
+        write_result(&other, Some(&parts[1])).unwrap();
+        assert!(merge_parts(&parts).is_err());
+    }
+
+    /// Lee el resultado publicado por el watch.
+    fn published(output: &Path) -> ResultData {
+        serde_json::from_slice(&std::fs::read(output).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn watch_publishes_changes_test() {
+        use crate::watch::watch_events;
+        use notify::event::{AccessKind, CreateKind, ModifyKind, RemoveKind};
+        use notify::{Event, EventKind};
+        use std::time::{Duration, Instant};
+
+        let pools = pools();
+        let dir = TempDir::new("watch");
+        let out = TempDir::new("watch-out");
+        let output = out.path().join("result.json");
+        let site1 = dir.write("site1.jsonl", "{\"texts\": [\"a\"], \"tags\": [\"x\"]}\n");
+        let site2 = dir.write("site2.jsonl", "{\"texts\": [\"b\"], \"tags\": [\"y\"]}\n");
+        dir.write("notas.txt", "no es jsonl");
+        let config = Config {
+            inputs: vec![dir.path().to_path_buf()],
+            output: Some(output.clone()),
+            ..Config::default()
+        };
+        let mut initial = process_files(&[site1.clone(), site2.clone()], &config, &pools, None);
+        process_totals(&mut initial);
+
+        let (sender, events) = std::sync::mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let events = events;
+                watch_events(&events, &config, &pools, &output)
+            });
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while !output.exists() {
+                assert!(Instant::now() < deadline, "no se publicó el resultado");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(initial, published(&output));
+
+            dir.write(
+                "site1.jsonl",
+                "{\"texts\": [\"a b c\"], \"tags\": [\"x\"]}\n",
+            );
+            std::fs::remove_file(&site2).unwrap();
+            let site3 = dir.write("site3.jsonl", "{\"texts\": [\"d\"], \"tags\": [\"z\"]}\n");
+            let notes = dir.path().join("notas.txt");
+            for (kind, path) in [
+                (EventKind::Access(AccessKind::Any), &site2),
+                (EventKind::Modify(ModifyKind::Any), &site1),
+                (EventKind::Remove(RemoveKind::File), &site2),
+                (EventKind::Create(CreateKind::File), &site3),
+                (EventKind::Modify(ModifyKind::Any), &notes),
+            ] {
+                sender
+                    .send(Ok(Event::new(kind).add_path(path.clone())))
+                    .unwrap();
+            }
+            drop(sender);
+        });
+
+        let mut expected = process_files(
+            &[site1, dir.path().join("site3.jsonl")],
+            &config,
+            &pools,
+            None,
+        );
+        process_totals(&mut expected);
+        assert_eq!(expected, published(&output));
+        assert!(!published(&output).sites.contains_key("site2"));
+    }
+
+    #[test]
+    fn watch_skips_vanished_files_test() {
+        use crate::watch::process_partials;
+
+        let pools = pools();
+        let dir = TempDir::new("watch-vanished");
+        let present = dir.write("site1.jsonl", "{\"texts\": [\"a\"], \"tags\": [\"x\"]}\n");
+        let vanished = dir.path().join("site2.jsonl");
+        let partials = process_partials(
+            &[present.clone(), vanished.clone()],
+            &Config::default(),
+            &pools,
+        );
+
+        assert_eq!(partials.len(), 2);
+        for (path, data) in partials {
+            assert_eq!(data.is_some(), path == present, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn progress_counters_test() {
+        use crate::progress::Progress;
+
+        let dir = TempDir::new("progress");
+        let files = vec![
+            dir.write("site1.jsonl", "x".repeat(100)),
+            dir.write("site2.jsonl", "x".repeat(50)),
+        ];
+        let progress = Progress::new(&files, 2);
+
+        // el hilo del test está fuera del pool: sus líneas van al contador extra
+        progress.start_file("site1");
+        progress.add_line(10);
+        progress.add_line(20);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        pool.install(|| progress.add_line(5));
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.lines, 3);
+        assert_eq!(snapshot.bytes, 35);
+        assert_eq!(snapshot.worker_lines[2], 2);
+        assert_eq!(snapshot.worker_lines[..2].iter().sum::<u64>(), 1);
+        assert!(progress.summary(&snapshot).starts_with("Archivos 0/2 |"));
+
+        progress.finish_file();
+        progress.skip_file(50);
+        assert!(progress
+            .summary(&progress.snapshot())
+            .starts_with("Archivos 2/2 |"));
+    }
+
+    #[test]
+    fn progress_summary_test() {
+        use crate::progress::{Progress, Snapshot};
+        use std::time::Duration;
+
+        let snapshot = |lines: u64, bytes: u64| Snapshot {
+            elapsed: Duration::from_secs(1),
+            lines,
+            bytes,
+            worker_lines: vec![lines, 0],
+        };
+
+        // sin bytes a procesar el porcentaje es 100% y no hay ETA
+        let empty = Progress::new(&[], 1);
+        assert_eq!(
+            empty.summary(&snapshot(0, 0)),
+            "Archivos 0/0 | 0.0/0.0 MB (100%) | 0 líneas/s | ETA -"
+        );
+
+        // los bytes salteados cuentan como hechos pero no para la velocidad
+        let dir = TempDir::new("progress-summary");
+        let files = vec![dir.write("site1.jsonl", vec![b'x'; 1024 * 1024])];
+        let progress = Progress::new(&files, 1);
+        progress.skip_file(512 * 1024);
+        let summary = progress.summary(&snapshot(10, 256 * 1024));
+        assert!(summary.starts_with("Archivos 1/1 |"), "{}", summary);
+        assert!(summary.contains("/1.0 MB (75%)"), "{}", summary);
+        assert!(summary.contains("| 10 líneas/s |"), "{}", summary);
+        assert!(summary.ends_with("ETA 1s"), "{}", summary);
+    }
+
+    #[test]
+    fn progress_workers_activity_test() {
+        use crate::progress::{Progress, Snapshot};
+        use std::time::Duration;
+
+        let progress = Progress::new(&[], 2);
+        let previous = Snapshot {
+            elapsed: Duration::ZERO,
+            ..progress.snapshot()
+        };
+        // fuera del pool: el archivo y las líneas van al contador extra, que no se muestra
+        progress.start_file("afuera");
+        progress.add_line(1);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        let index = pool.install(|| {
+            progress.start_file("adentro");
+            progress.add_line(1);
+            progress.add_line(1);
+            rayon::current_thread_index().unwrap()
+        });
+        let snapshot = Snapshot {
+            elapsed: Duration::from_secs(1),
+            ..progress.snapshot()
+        };
+
+        let activity = progress.workers_activity(&snapshot, &previous);
+        let other = 1 - index;
+        assert_eq!(
+            activity.split(" | ").collect::<Vec<_>>()[index],
+            format!("w{} 2/s [adentro]", index)
+        );
+        assert_eq!(
+            activity.split(" | ").collect::<Vec<_>>()[other],
+            format!("w{} 0/s [-]", other)
+        );
+        assert!(!activity.contains("afuera"));
+        assert_eq!(snapshot.lines, 3);
+    }
+
+    #[test]
+    fn perf_report_test() {
+        use crate::report::PerfReport;
+
+        let pools = pools();
+        let _session = Session::start();
+        let dir = TempDir::new("report");
+        let report_path = dir.path().join("report.json");
+        let files = list_files("/test1");
+        let config = Config {
+            report: Some(report_path.clone()),
+            ..Config::default()
+        };
+        let mut report = PerfReport::default();
+        process_files(&files, &config, &pools, Some(&mut report));
+        report.write(&report_path).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&report_path).unwrap()).unwrap();
+        let reported = written["files"].as_array().unwrap();
+        assert_eq!(reported.len(), files.len());
+        for (file, path) in reported.iter().zip(&files) {
+            assert_eq!(file["path"], path.display().to_string());
+            assert_eq!(file["status"], "complete");
+            assert_eq!(file["source"], "processed");
+            // cada línea suma su salto de línea, aunque la última no lo tenga
+            let size = path.metadata().unwrap().len();
+            assert!((size..=size + 1).contains(&file["bytes"].as_u64().unwrap()));
+            assert_eq!(file["lines"], 2);
+            assert!(file["parse_ms"].as_f64().unwrap() > 0.0);
+            assert!(file["aggregation_ms"].as_f64().unwrap() > 0.0);
+            assert!(file["finish_ms"].as_f64() >= file["start_ms"].as_f64());
+        }
+        assert!(written["phases"]["processing_ms"].as_f64().unwrap() > 0.0);
+
+        // sin reporte no se mide el parseo de cada línea
+        let untimed = process_file_with_state(&files[0], &Config::default(), &pools, None, None);
+        assert_eq!(untimed.stats.lines, 2);
+        assert_eq!(untimed.stats.parse_ns, 0);
+    }
+
+    #[test]
+    fn sharded_report_test() {
+        use crate::backends::sharded::DEFAULT_SHARDS;
+        use crate::config::Backend;
+        use crate::report::PerfReport;
+
+        let pools = pools();
+        let dir = TempDir::new("sharded-report");
+        let report_path = dir.path().join("report.json");
+        let files = list_files("/test1");
+        let config = Config {
+            backend: Backend::Sharded,
+            report: Some(report_path.clone()),
+            ..Config::default()
+        };
+        let mut report = PerfReport::default();
+        let result = process_files(&files, &config, &pools, Some(&mut report));
+        report.write(&report_path).unwrap();
+        assert_eq!(
+            result,
+            process_files(&files, &Config::default(), &pools, None)
+        );
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&report_path).unwrap()).unwrap();
+        let shards = written["shards"].as_array().unwrap();
+        assert_eq!(shards.len(), DEFAULT_SHARDS);
+        let locks: u64 = shards.iter().map(|s| s["locks"].as_u64().unwrap()).sum();
+        assert!(locks > 0);
+        assert!(shards
+            .iter()
+            .all(|s| s["contended"].as_u64().is_some() && s["wait_ms"].as_f64().is_some()));
+    }
+
+    #[test]
+    fn bench_measurements_test() {
+        use crate::bench::{recommend, to_csv, worker_counts, Measurement};
+
+        assert_eq!(worker_counts(1), vec![1]);
+        assert_eq!(worker_counts(4), vec![1, 2, 4]);
+        assert_eq!(worker_counts(6), vec![1, 2, 4, 6]);
+
+        let measurements: Vec<Measurement> = [(1, 8.0), (2, 4.0), (4, 3.9), (8, 3.8)]
+            .into_iter()
+            .map(|(workers, seconds)| Measurement::new(workers, seconds, 8.0))
+            .collect();
+        assert_eq!(measurements[1].speedup, 2.0);
+        assert_eq!(measurements[1].efficiency, 1.0);
+        assert_eq!(measurements[1].serial_fraction, Some(0.0));
+        assert_eq!(measurements[0].serial_fraction, None);
+        // 3.9 s está a menos de 5% del mejor tiempo (3.8 s), 4 s no
+        assert_eq!(recommend(&measurements), Some(4));
+        assert_eq!(recommend(&[]), None);
+
+        let csv = to_csv(&measurements[..2]);
+        assert_eq!(
+            csv,
+            "workers,seconds,speedup,efficiency,serial_fraction\n\
+             1,8.000000,1.000000,1.000000,\n\
+             2,4.000000,2.000000,1.000000,0.000000\n"
+        );
+    }
+
+    #[test]
+    fn schedules_test() {
+        let pools = pools();
+        let files = list_files("/test1");
+        let input = Config {
+            schedule: crate::config::Schedule::Input,
+            ..Config::default()
+        };
+
+        assert_eq!(
+            process_files(&files, &input, &pools, None),
+            process_files(&files, &Config::default(), &pools, None)
+        );
+    }
+
+    #[test]
+    fn schedule_largest_first_test() {
+        use crate::report::PerfReport;
+
+        let dir = TempDir::new("schedule");
+        let line = "{\"texts\": [\"a b\"], \"tags\": [\"x\"]}\n";
+        let files = vec![
+            dir.write("site1.jsonl", line),
+            dir.write("site2.jsonl", line.repeat(50)),
+            dir.write("site3.jsonl", line.repeat(10)),
+        ];
+        let order: Vec<usize> = schedule_largest_first(&files)
+            .into_iter()
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(order, [1, 2, 0]);
+
+        // con un solo worker los archivos empiezan en ese orden, el más grande primero
+        let single = Pools::new(1, 1).unwrap();
+        let config = Config {
+            report: Some(dir.path().join("report.json")),
+            ..Config::default()
+        };
+        let mut report = PerfReport::default();
+        process_files(&files, &config, &single, Some(&mut report));
+        let written = serde_json::to_value(&report).unwrap();
+        let start = |index: usize| written["files"][index]["start_ms"].as_f64().unwrap();
+        assert!(start(1) <= start(2) && start(2) <= start(0));
+        assert!(start(1) < start(0));
+    }
+
+    #[test]
+    fn scoped_pools_test() {
+        let files = list_files("/test1");
+        let single = Pools::new(1, 1).unwrap();
+        let separate = Pools::new(3, 2).unwrap();
+        assert_eq!(separate.io().current_num_threads(), 3);
+        assert_eq!(separate.parse().current_num_threads(), 2);
+
+        let budgeted = Config {
+            memory_budget: Some(8 * 1024),
+            ..Config::default()
+        };
+        let expected = process_files(&files, &Config::default(), &single, None);
+        assert_eq!(
+            expected,
+            process_files(&files, &Config::default(), &separate, None)
+        );
+        assert_eq!(expected, process_files(&files, &budgeted, &separate, None));
+    }
+
+    #[test]
+    fn memory_budget_test() {
+        use crate::budget::MemoryBudget;
+
+        let pools = pools();
+        let dir = TempDir::new("budget");
+        let lines: Vec<String> = (0..3000)
+            .map(|i| format!("{{\"texts\": [\"a {}\"], \"tags\": [\"t{}\"]}}", i, i % 5))
+            .collect();
+        dir.write("site5.jsonl", lines.join("\n"));
+        let mut files = list_files("/test1");
+        files.extend(dir.files());
+
+        let budgeted = Config {
+            memory_budget: Some(8 * 1024),
+            ..Config::default()
+        };
+        assert_eq!(
+            process_files(&files, &Config::default(), &pools, None),
+            process_files(&files, &budgeted, &pools, None)
+        );
+
+        // leyendo desde este hilo, la lectura se adelanta al parseo y tiene que esperar
+        let _session = Session::start();
+        let budget = MemoryBudget::new(8 * 1024);
+        for path in &files {
+            process_file_with_state(path, &budgeted, &pools, None, Some(&budget));
+        }
+        let report = budget.report();
+        assert!(report.peak_in_flight_mb > 0.0);
+        assert!(report.peak_in_flight_mb <= report.limit_mb);
+        assert!(report.waits > 0);
+    }
+
+    #[test]
+    fn async_take_lines_test() {
+        use crate::backends::async_io::take_lines;
+
+        let mut pending = b"ab\ncd\nef\ngh".to_vec();
+        assert_eq!(take_lines(&mut pending, 7), Some(b"ab\ncd\n".to_vec()));
+        assert_eq!(pending, b"ef\ngh");
+        // una línea más larga que el lote se envía sola
+        assert_eq!(take_lines(&mut pending, 1), Some(b"ef\n".to_vec()));
+        assert_eq!(take_lines(&mut pending, 8), None);
+        assert_eq!(pending, b"gh");
+    }
+
+    #[test]
+    fn buffered_and_gzip_files_test() {
+        let pools = pools();
+        let files = list_files("/test1");
+        let expected = process_files(&files, &Config::default(), &pools, None);
+
+        let buffered = Config {
+            no_mmap: true,
+            ..Config::default()
+        };
+        assert_eq!(expected, process_files(&files, &buffered, &pools, None));
+
+        let gzip_dir = TempDir::new("gzip");
+        for path in &files {
+            let gzip_path = gzip_dir.path().join(format!(
+                "{}.gz",
+                path.file_name().unwrap().to_string_lossy()
+            ));
+            let mut encoder = flate2::write::GzEncoder::new(
+                std::fs::File::create(gzip_path).unwrap(),
+                flate2::Compression::default(),
+            );
+            std::io::Write::write_all(&mut encoder, &std::fs::read(path).unwrap()).unwrap();
+            encoder.finish().unwrap();
+        }
+        let gzip = process_files(&gzip_dir.files(), &Config::default(), &pools, None);
+        let asynchronous = Config {
+            backend: crate::config::Backend::Async,
+            ..Config::default()
+        };
+        let gzip_async = process_files(&gzip_dir.files(), &asynchronous, &pools, None);
+
+        assert_eq!(expected, gzip);
+        assert_eq!(expected, gzip_async);
+    }
+
+    #[test]
+    fn line_endings_test() {
+        use crate::config::Backend;
+
+        let pools = pools();
+        let _session = Session::start();
+        let lines: Vec<String> = (0..200)
+            .map(|i| format!("{{\"texts\": [\"a b {}\"], \"tags\": [\"t{}\"]}}", i, i % 7))
+            .collect();
+        let expected_dir = TempDir::new("lf");
+        expected_dir.write("site1.jsonl", lines.join("\n"));
+        let expected = process_files(&expected_dir.files(), &Config::default(), &pools, None);
+
+        // CRLF y líneas vacías (también con solo `\r`) intercaladas
+        let dir = TempDir::new("crlf");
+        let contents: String = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| match i % 3 {
+                0 => format!("{}\r\n\r\n", line),
+                1 => format!("{}\n\n", line),
+                _ => format!("{}\r\n", line),
+            })
+            .collect();
+        let path = dir.write("site1.jsonl", contents);
+        let files = dir.files();
+
+        let configs = [
+            Config::default(),
+            Config {
+                no_mmap: true,
+                ..Config::default()
+            },
+            Config {
+                chunk_size: Some(64),
+                no_mmap: true,
+                ..Config::default()
+            },
+        ];
+        for config in &configs {
+            assert_eq!(expected, process_files(&files, config, &pools, None));
+            let result = try_process_file_with_state(&path, config, &pools, None, None).unwrap();
+            assert_eq!(result.stats.lines, lines.len() as u64);
+        }
+        for backend in [
+            Backend::Pipeline,
+            Backend::Threads,
+            Backend::Actors,
+            Backend::Sharded,
+            Backend::Async,
+        ] {
+            let config = Config {
+                backend,
+                chunk_size: Some(64),
+                ..Config::default()
+            };
+            assert_eq!(expected, process_files(&files, &config, &pools, None));
+        }
+    }
+
+    /// Genera el `ResultData` de un archivo creando un `ResultData` por línea y sumándolos,
+    /// como se hacía antes de acumular por worker.
+    fn per_line_result(path: &std::path::Path, site: &str) -> ResultData {
+        let contents = std::fs::read_to_string(path).unwrap();
+        contents
+            .lines()
+            .map(|line| {
+                let line_struct: LineJsonStructure = serde_json::from_str(line).unwrap();
+                let words = line_struct.words.0;
+                let tags: HashMap<String, TagData> = line_struct
+                    .tags
+                    .iter()
+                    .map(|Tag(tag)| (tag.to_string(), TagData::new(1, words)))
+                    .collect();
+                let site_data = SiteData::new(1, words, tags.clone());
+                ResultData::new(PADRON, HashMap::from([(site.to_string(), site_data)]), tags)
+            })
+            .fold(
+                ResultData::new(PADRON, HashMap::new(), HashMap::new()),
+                |acc, b| acc + b,
+            )
+    }
+
+    #[test]
+    fn worker_accumulation_matches_per_line_test() {
+        let pools = pools();
+        let dir = TempDir::new("fold");
+        let lines: Vec<String> = (0..5000)
+            .map(|i| {
+                format!(
+                    "{{\"texts\": [\"{}\", \"a b\\tc\"], \"tags\": [\"t{}\", \"t{}\"]}}",
+                    "x ".repeat(i % 7),
+                    i % 13,
+                    i % 101
+                )
+            })
+            .collect();
+        let path = dir.write("site3.jsonl", lines.join("\n"));
+
+        let expected = per_line_result(&path, "site3");
+        let files = dir.files();
+        let mmap = process_files(&files, &Config::default(), &pools, None);
+        let chunked = process_files(
+            &files,
+            &Config {
+                chunk_size: Some(1),
+                no_mmap: true,
+                ..Config::default()
+            },
+            &pools,
+            None,
+        );
+
+        assert_eq!(expected, mmap);
+        assert_eq!(expected, chunked);
+        for path in list_files("/test1") {
+            let site = path.file_stem().unwrap().to_string_lossy().to_string();
+            assert_eq!(
+                per_line_result(&path, &site).sites[&site],
+                process_files(&[path], &Config::default(), &pools, None).sites[&site]
+            );
+        }
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn simd_parser_test() {
+        use crate::config::Parser;
+
+        let pools = pools();
+        let dir = TempDir::new("simd");
+        dir.write(
+            "site4.jsonl",
+            concat!(
+                "{\"texts\": [\"uno dos\", \"tres\\ncuatro \\u00e1\"], \"tags\": [\"t\\\"1\", \"ñ\"]}\n",
+                "{\"tags\": [\"ñ\", \"t2\"], \"texts\": [\"\", \"  cinco\\t seis  \"]}\n",
+                "no es json\n",
+            ),
+        );
+        let mut files = list_files("/test1");
+        files.extend(dir.files());
+
+        let simd = Config {
+            parser: Parser::Simd,
+            ..Config::default()
+        };
+        let expected = process_files(&files, &Config::default(), &pools, None);
+        let actual = process_files(&files, &simd, &pools, None);
+
+        assert_eq!(expected, actual);
+        assert_eq!(expected.sites["site4"].words, 7);
+        assert_eq!(expected.tags["t\"1"].questions, 1);
+    }
+
+    #[test]
+    fn megabyte_flags_test() {
+        let args = |values: &[&str]| -> Vec<String> {
+            values.iter().map(|value| value.to_string()).collect()
+        };
+
+        let config = Config::from_args(&args(&["1", "--chunk-size", "2"])).unwrap();
+        assert_eq!(config.chunk_size, Some(2 * 1024 * 1024));
+        // un valor que no entra en un u64 es un error de configuración, no un overflow
+        assert!(Config::from_args(&args(&["1", "--chunk-size", "99999999999999"])).is_err());
+
+        let config = Config::from_args(&args(&["1", "--memory-budget", "3"])).unwrap();
+        assert_eq!(config.memory_budget, Some(3 * 1024 * 1024));
+        assert!(Config::from_args(&args(&["1", "--memory-budget", "99999999999999"])).is_err());
     }
 }
@@ -0,0 +1,244 @@
+//! Este módulo implementa el subcomando `watch`, que mantiene actualizado el archivo de
+//! resultado a medida que se agregan, modifican o eliminan archivos JSONL de entrada.
+
+use crate::budget::MemoryBudget;
+use crate::cancel::is_cancelled;
+use crate::config::Config;
+use crate::intern::Session;
+use crate::output::write_result;
+use crate::pools::Pools;
+use crate::processors::{
+    absolute_paths, input_dirs, input_files, process_totals, try_process_file_with_state, PADRON,
+};
+use crate::structs::ResultData;
+use notify::event::{AccessKind, AccessMode};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Intervalo con el que se verifica si se recibió una señal de cancelación.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Tiempo sin eventos que se espera antes de reprocesar los archivos modificados.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Indica si una ruta corresponde a un archivo JSONL de entrada.
+///
+/// # Arguments
+///
+/// * `path` - Ruta a verificar.
+fn is_jsonl(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "jsonl")
+}
+
+/// Indica si un evento puede haber cambiado el contenido de un archivo.
+///
+/// # Arguments
+///
+/// * `kind` - Tipo de evento.
+fn is_relevant(kind: &EventKind) -> bool {
+    match kind {
+        EventKind::Access(AccessKind::Close(AccessMode::Write)) => true,
+        EventKind::Access(_) => false,
+        _ => true,
+    }
+}
+
+/// Espera el próximo lote de cambios. Luego del primer evento relevante, sigue acumulando
+/// eventos hasta que pase `DEBOUNCE` sin recibir ninguno.
+///
+/// # Arguments
+///
+/// * `events` - Canal de eventos del watcher.
+///
+/// # Returns
+///
+/// Las rutas JSONL modificadas, o `None` si hay que dejar de observar.
+pub(crate) fn next_changes(events: &Receiver<notify::Result<Event>>) -> Option<HashSet<PathBuf>> {
+    let mut changed = HashSet::new();
+    loop {
+        let timeout = if changed.is_empty() {
+            POLL_INTERVAL
+        } else {
+            DEBOUNCE
+        };
+        match events.recv_timeout(timeout) {
+            Ok(Ok(event)) if is_relevant(&event.kind) => {
+                changed.extend(event.paths.into_iter().filter(|path| is_jsonl(path)));
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("Error del watcher: {}", e),
+            Err(RecvTimeoutError::Timeout) if !changed.is_empty() => return Some(changed),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) if !changed.is_empty() => return Some(changed),
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+        if is_cancelled() {
+            return None;
+        }
+    }
+}
+
+/// Combina los resultados parciales por archivo, calcula los totals y reescribe el archivo de
+/// salida de forma atómica.
+///
+/// # Arguments
+///
+/// * `partials` - Resultado parcial de cada archivo.
+/// * `output` - Archivo de salida.
+fn publish(partials: &HashMap<PathBuf, ResultData>, output: &Path) {
+    let mut result_data = partials.values().cloned().fold(
+        ResultData::new(PADRON, HashMap::new(), HashMap::new()),
+        |acc, b| acc + b,
+    );
+    process_totals(&mut result_data);
+
+    match write_result(&result_data, Some(output)) {
+        Ok(()) => eprintln!(
+            "Resultado actualizado en {} ({} archivos)",
+            output.display(),
+            partials.len()
+        ),
+        Err(e) => eprintln!("Error al escribir {}: {}", output.display(), e),
+    }
+}
+
+/// Procesa los archivos indicados y devuelve su resultado parcial. Un archivo que desaparece
+/// o no se puede leer mientras se procesa se omite, sin detener el watch.
+///
+/// Los nombres de cada resultado se resuelven dentro de la sesión del lote, para que la tabla
+/// del interner se vacíe entre un lote y el siguiente.
+///
+/// # Arguments
+///
+/// * `paths` - Archivos a procesar.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools del procesamiento.
+///
+/// # Returns
+///
+/// El resultado parcial de cada archivo, o `None` para los que se omitieron.
+pub(crate) fn process_partials(
+    paths: &[PathBuf],
+    config: &Config,
+    pools: &Pools,
+) -> Vec<(PathBuf, Option<ResultData>)> {
+    let _session = Session::start();
+    let budget = config.memory_budget.map(MemoryBudget::new);
+    pools.io().install(|| {
+        paths
+            .par_iter()
+            .map(|path| {
+                match try_process_file_with_state(path, config, pools, None, budget.as_ref()) {
+                    Ok(result) => (path.clone(), Some(result.data.into_result_data(PADRON))),
+                    Err(e) => {
+                        eprintln!("Se omite {}: {}", path.display(), e);
+                        (path.clone(), None)
+                    }
+                }
+            })
+            .collect()
+    })
+}
+
+/// Actualiza los resultados parciales con los de los archivos reprocesados. Los archivos que se
+/// omitieron dejan de contarse.
+///
+/// # Arguments
+///
+/// * `partials` - Resultado parcial de cada archivo.
+/// * `updated` - Resultados de los archivos reprocesados.
+fn update_partials(
+    partials: &mut HashMap<PathBuf, ResultData>,
+    updated: Vec<(PathBuf, Option<ResultData>)>,
+) {
+    for (path, data) in updated {
+        match data {
+            Some(data) => partials.insert(path, data),
+            None => partials.remove(&path),
+        };
+    }
+}
+
+/// Procesa los archivos de entrada, publica el resultado y lo vuelve a publicar ante cada lote
+/// de cambios, hasta que se cierre el canal de eventos o se reciba SIGINT/SIGTERM.
+///
+/// Los archivos se leen siempre con buffer, como con `--no-mmap`, porque pueden truncarse
+/// mientras se procesan y con un mapeo en memoria eso termina el proceso con SIGBUS.
+///
+/// Los resultados parciales se guardan por ruta absoluta, porque el watcher informa los
+/// eventos con rutas absolutas aunque `--input` sea relativo.
+///
+/// # Arguments
+///
+/// * `events` - Canal de eventos del watcher.
+/// * `config` - Configuración del procesamiento.
+/// * `pools` - ThreadPools del procesamiento.
+/// * `output` - Archivo de salida.
+pub(crate) fn watch_events(
+    events: &Receiver<notify::Result<Event>>,
+    config: &Config,
+    pools: &Pools,
+    output: &Path,
+) {
+    let config = &Config {
+        no_mmap: true,
+        ..config.clone()
+    };
+    let files: Vec<PathBuf> = absolute_paths(input_files(config))
+        .into_iter()
+        .filter(|path| is_jsonl(path))
+        .collect();
+    let mut partials = HashMap::new();
+    update_partials(&mut partials, process_partials(&files, config, pools));
+    if is_cancelled() {
+        return;
+    }
+    publish(&partials, output);
+
+    while let Some(changed) = next_changes(events) {
+        let (present, removed): (Vec<PathBuf>, Vec<PathBuf>) =
+            absolute_paths(changed.into_iter().collect())
+                .into_iter()
+                .partition(|path| path.is_file());
+        for path in removed {
+            partials.remove(&path);
+        }
+        let updated = process_partials(&present, config, pools);
+        if is_cancelled() {
+            return;
+        }
+        update_partials(&mut partials, updated);
+        publish(&partials, output);
+    }
+}
+
+/// Procesa los archivos de entrada y mantiene actualizado el archivo de salida ante cada cambio
+/// en los directorios de entrada, hasta recibir SIGINT/SIGTERM.
+///
+/// # Arguments
+///
+/// * `config` - Configuración del procesamiento. Debe tener `output`.
+/// * `pools` - ThreadPools del procesamiento.
+pub fn watch(config: &Config, pools: &Pools) {
+    let output = config.output.as_deref().expect("watch requiere --output");
+
+    let (sender, events) = channel();
+    let mut watcher = match notify::recommended_watcher(sender) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Error al crear el watcher: {}", e);
+            std::process::exit(1);
+        }
+    };
+    for dir in input_dirs(config) {
+        if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+            eprintln!("Error al observar {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }
+    }
+    watch_events(&events, config, pools, output);
+}
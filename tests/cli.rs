@@ -0,0 +1,382 @@
+//! Tests que ejecutan el binario, para los comportamientos que dependen de señales y de
+//! procesos hijos.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Ruta del binario compilado por cargo para los tests.
+const BINARY: &str = env!("CARGO_BIN_EXE_proyecto_2024-1c-tp1-pgallino");
+
+/// TempDir: directorio temporal de un test, que se borra al salir de scope.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("tp1-cli-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Genera `count` líneas de entrada válidas.
+fn lines(count: usize) -> String {
+    (0..count)
+        .map(|i| {
+            format!(
+                "{{\"texts\": [\"a b {}\"], \"tags\": [\"t{}\"]}}\n",
+                i,
+                i % 3
+            )
+        })
+        .collect()
+}
+
+/// Parsea la salida JSON de una corrida.
+fn result_json(output: &Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn sigterm_writes_partial_result_test() {
+    let dir = TempDir::new("sigterm");
+    std::fs::write(dir.path().join("site1.jsonl"), lines(10)).unwrap();
+    // un FIFO permite mandar la señal mientras el archivo todavía se está leyendo
+    let fifo = dir.path().join("site2.jsonl");
+    assert!(Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap()
+        .success());
+
+    let child = Command::new(BINARY)
+        .args(["1", "--input"])
+        .arg(dir.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut writer = std::fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+    writer.write_all(lines(10).as_bytes()).unwrap();
+    thread::sleep(Duration::from_millis(200));
+    // SAFETY: kill solo envía una señal al proceso hijo.
+    assert_eq!(unsafe { libc::kill(child.id() as i32, libc::SIGTERM) }, 0);
+    thread::sleep(Duration::from_millis(200));
+    writer.write_all(lines(10).as_bytes()).unwrap();
+    drop(writer);
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(143));
+    let result = result_json(&output);
+    let incomplete = &result["incomplete"];
+    assert_eq!(
+        incomplete["partial_files"][0].as_str().unwrap(),
+        fifo.display().to_string()
+    );
+    assert_eq!(incomplete["complete_files"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn coordinator_and_worker_processes_test() {
+    let dir = TempDir::new("distributed");
+    let input = dir.path().join("in");
+    std::fs::create_dir(&input).unwrap();
+    for site in 1..=3 {
+        std::fs::write(input.join(format!("site{}.jsonl", site)), lines(50 * site)).unwrap();
+    }
+    let local = Command::new(BINARY)
+        .args(["1", "--input", "in"])
+        .current_dir(dir.path())
+        .stderr(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(local.status.success());
+    // un archivo que el worker no puede abrir no lo hace terminar
+    let missing = input.join("site9.jsonl");
+    std::os::unix::fs::symlink(dir.path().join("no-existe"), &missing).unwrap();
+
+    // el coordinador recibe la entrada relativa a su directorio y los workers corren en otro
+    let mut coordinator = Command::new(BINARY)
+        .args([
+            "coordinator",
+            "1",
+            "--listen",
+            "127.0.0.1:0",
+            "--input",
+            "in",
+        ])
+        .current_dir(dir.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = BufReader::new(coordinator.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let address = line
+        .split_whitespace()
+        .find(|word| word.starts_with("127.0.0.1:"))
+        .unwrap_or_else(|| panic!("dirección no encontrada en {:?}", line))
+        .to_string();
+    let drain = thread::spawn(move || {
+        let mut rest = String::new();
+        stderr.read_to_string(&mut rest).unwrap();
+    });
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            Command::new(BINARY)
+                .args(["worker", "1", "--connect", &address])
+                .current_dir("/")
+                .stderr(Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+
+    let output = coordinator.wait_with_output().unwrap();
+    drain.join().unwrap();
+    for mut worker in workers {
+        assert!(worker.wait().unwrap().success());
+    }
+    // resultado parcial sin señal: no usa el código de SIGINT
+    assert_eq!(output.status.code(), Some(2));
+    let (result, expected) = (result_json(&output), result_json(&local));
+    assert_eq!(result["sites"], expected["sites"]);
+    assert_eq!(result["tags"], expected["tags"]);
+    assert_eq!(result["totals"], expected["totals"]);
+    let incomplete = &result["incomplete"];
+    assert_eq!(
+        incomplete["not_processed_files"][0].as_str().unwrap(),
+        missing.display().to_string()
+    );
+    assert_eq!(incomplete["complete_files"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn merge_partial_shard_exit_code_test() {
+    let dir = TempDir::new("merge");
+    let input = dir.path().join("in");
+    std::fs::create_dir(&input).unwrap();
+    std::fs::write(input.join("site1.jsonl"), lines(10)).unwrap();
+    let run = |args: &[&str]| {
+        Command::new(BINARY)
+            .args(args)
+            .current_dir(dir.path())
+            .stderr(Stdio::null())
+            .output()
+            .unwrap()
+    };
+    assert!(
+        run(&["plan", "1", "--input", "in", "--output", "manifest.json"])
+            .status
+            .success()
+    );
+    let shard = run(&["1", "--manifest", "manifest.json", "--shard", "0/1"]);
+    assert!(shard.status.success());
+
+    // un shard parcial porque no pudo abrir un archivo, sin que haya llegado una señal
+    let mut part = result_json(&shard);
+    part["incomplete"] = serde_json::json!({
+        "complete_files": [],
+        "partial_files": [],
+        "not_processed_files": [input.join("site2.jsonl")],
+    });
+    std::fs::write(dir.path().join("part.json"), part.to_string()).unwrap();
+    let output = run(&["merge", "1", "--part", "part.json"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(result_json(&output)["sites"], result_json(&shard)["sites"]);
+
+    // el mismo shard dos veces no se combina
+    let output = run(&["merge", "1", "--part", "part.json", "--part", "part.json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+}
+
+/// KillOnDrop: proceso hijo que se termina al salir de scope, aunque el test falle.
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Espera a que `watch` publique un resultado distinto de `previous` y lo devuelve.
+fn next_published(output: &Path, previous: &serde_json::Value) -> serde_json::Value {
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let published = std::fs::read(output)
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok());
+        match published {
+            Some(result) if result != *previous => return result,
+            _ => {
+                assert!(
+                    std::time::Instant::now() < deadline,
+                    "no se publicó el resultado"
+                );
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+}
+
+#[test]
+fn watch_relative_input_test() {
+    let dir = TempDir::new("watch");
+    let input = dir.path().join("in");
+    std::fs::create_dir(&input).unwrap();
+    std::fs::write(input.join("site1.jsonl"), lines(1)).unwrap();
+    std::fs::write(input.join("site2.jsonl"), lines(1)).unwrap();
+    let output = dir.path().join("out.json");
+
+    // con `--input` relativo los eventos llegan con rutas absolutas
+    let child = Command::new(BINARY)
+        .args(["watch", "1", "--input", "in", "--output", "out.json"])
+        .current_dir(dir.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let _watch = KillOnDrop(child);
+    let questions =
+        |result: &serde_json::Value, site: &str| result["sites"][site]["questions"].clone();
+    let result = next_published(&output, &serde_json::Value::Null);
+    assert_eq!(questions(&result, "site1"), 1);
+    assert_eq!(questions(&result, "site2"), 1);
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(input.join("site1.jsonl"))
+        .unwrap();
+    file.write_all(lines(1).as_bytes()).unwrap();
+    drop(file);
+    let result = next_published(&output, &result);
+    assert_eq!(questions(&result, "site1"), 2);
+    assert_eq!(questions(&result, "site2"), 1);
+
+    std::fs::remove_file(input.join("site2.jsonl")).unwrap();
+    let result = next_published(&output, &result);
+    assert_eq!(questions(&result, "site1"), 2);
+    assert!(result["sites"].get("site2").is_none());
+}
+
+/// Devuelve los procesos hijos de un proceso, lanzados desde cualquiera de sus hilos.
+fn children(pid: u32) -> Vec<u32> {
+    std::fs::read_dir(format!("/proc/{}/task", pid))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|task| std::fs::read_to_string(task.path().join("children")).ok())
+        .flat_map(|children| {
+            children
+                .split_whitespace()
+                .map(|child| child.parse().unwrap())
+                .collect::<Vec<u32>>()
+        })
+        .collect()
+}
+
+/// Indica si un proceso tiene abierto un archivo.
+fn has_open(pid: u32, path: &Path) -> bool {
+    std::fs::read_dir(format!("/proc/{}/fd", pid))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .any(|fd| std::fs::read_link(fd.path()).is_ok_and(|target| target == path))
+}
+
+/// Indica si un proceso terminó y cerró sus archivos: ya no existe o todos sus hilos son
+/// zombies. Mientras termina, `/proc/<pid>/fd` deja de poder leerse antes de que se cierren sus
+/// archivos, y el hilo principal puede quedar zombie antes que el resto.
+fn exited(pid: u32) -> bool {
+    let Ok(tasks) = std::fs::read_dir(format!("/proc/{}/task", pid)) else {
+        return true;
+    };
+    tasks.flatten().all(|task| {
+        std::fs::read_to_string(task.path().join("stat")).map_or(true, |stat| {
+            stat.rsplit_once(") ")
+                .is_some_and(|(_, fields)| fields.starts_with(['Z', 'X']))
+        })
+    })
+}
+
+#[test]
+fn killed_child_process_is_retried_test() {
+    let dir = TempDir::new("processes");
+    let input = dir.path().join("in");
+    std::fs::create_dir(&input).unwrap();
+    std::fs::write(input.join("site1.jsonl"), lines(30)).unwrap();
+    std::fs::write(input.join("site2.jsonl"), lines(10)).unwrap();
+    let local = Command::new(BINARY)
+        .args(["1", "--input"])
+        .arg(&input)
+        .stderr(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(local.status.success());
+    // con un FIFO el hijo de site2 queda leyendo hasta que se le escriba
+    let fifo = input.join("site2.jsonl");
+    std::fs::remove_file(&fifo).unwrap();
+    assert!(Command::new("mkfifo")
+        .arg(&fifo)
+        .status()
+        .unwrap()
+        .success());
+
+    let parent = Command::new(BINARY)
+        .args(["1", "--processes", "2", "--input"])
+        .arg(&input)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // abrir el FIFO espera a que el hijo lo abra para leer
+    let writer = std::fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+    // el descriptor del hijo puede aparecer en /proc un poco después
+    let reader = (0..50)
+        .find_map(|_| {
+            let reader = children(parent.id())
+                .into_iter()
+                .find(|&child| has_open(child, &fifo));
+            if reader.is_none() {
+                thread::sleep(Duration::from_millis(20));
+            }
+            reader
+        })
+        .expect("ningún hijo abrió el FIFO");
+    // SAFETY: kill solo envía una señal al proceso hijo.
+    assert_eq!(unsafe { libc::kill(reader as i32, libc::SIGKILL) }, 0);
+    drop(writer);
+    // hasta que el hijo termina, un nuevo writer se emparejaría con su lectura
+    while !exited(reader) {
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    // el archivo se reintenta en un hijo nuevo, que vuelve a abrir el FIFO
+    let mut writer = std::fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+    writer.write_all(lines(10).as_bytes()).unwrap();
+    drop(writer);
+
+    let output = parent.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let (result, expected) = (result_json(&output), result_json(&local));
+    assert!(result.get("incomplete").is_none());
+    assert_eq!(result["sites"], expected["sites"]);
+    assert_eq!(result["tags"], expected["tags"]);
+    assert_eq!(result["totals"], expected["totals"]);
+}